@@ -0,0 +1,109 @@
+use crate::config::AuthConfig;
+use anyhow::{anyhow, Context, Result};
+use base64::Engine;
+use cookie_store::{CookieStore, RawCookie};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, AUTHORIZATION};
+use std::io::BufReader;
+use std::path::Path;
+use url::Url;
+
+/// Reads a credential from the environment, naming the variable when it is missing.
+pub(crate) fn read_env(var: &str) -> Result<String> {
+    std::env::var(var).with_context(|| format!("environment variable {} is not set", var))
+}
+
+/// Builds the default headers for a scope: static headers first, then the
+/// `Authorization` header from Basic or Bearer credentials.
+pub fn auth_headers(auth: &AuthConfig) -> Result<HeaderMap> {
+    let mut headers = HeaderMap::new();
+    for (name, value) in &auth.headers {
+        let name = HeaderName::from_bytes(name.as_bytes())?;
+        headers.insert(name, HeaderValue::from_str(value)?);
+    }
+
+    let authorization = if let Some(basic) = &auth.basic {
+        let username = read_env(&basic.username_env)?;
+        let password = read_env(&basic.password_env)?;
+        let encoded = base64::engine::general_purpose::STANDARD.encode(format!("{}:{}", username, password));
+        Some(format!("Basic {}", encoded))
+    } else if let Some(token_env) = &auth.bearer_token_env {
+        Some(format!("Bearer {}", read_env(token_env)?))
+    } else {
+        None
+    };
+
+    if let Some(authorization) = authorization {
+        let mut value = HeaderValue::from_str(&authorization)?;
+        value.set_sensitive(true);
+        headers.insert(AUTHORIZATION, value);
+    }
+
+    Ok(headers)
+}
+
+/// Loads the persistent JSON cookie jar, or an empty store if the file does not exist yet.
+pub fn load_cookie_jar(path: &str) -> Result<CookieStore> {
+    if !Path::new(path).exists() {
+        return Ok(CookieStore::default());
+    }
+    let file = std::fs::File::open(path)?;
+    cookie_store::serde::json::load(BufReader::new(file))
+        .map_err(|e| anyhow!("failed to load cookie jar {}: {}", path, e))
+}
+
+/// Saves the cookie jar including session cookies, so a later crawl can reuse the session.
+pub fn save_cookie_jar(store: &CookieStore, path: &str) -> Result<()> {
+    let mut file = std::fs::File::create(path)?;
+    cookie_store::serde::json::save_incl_expired_and_nonpersistent(store, &mut file)
+        .map_err(|e| anyhow!("failed to save cookie jar {}: {}", path, e))
+}
+
+/// Imports cookies from a Netscape `cookies.txt` file (the format exported by curl and
+/// browser extensions). Expired entries are skipped. Returns the number of cookies imported.
+pub fn import_netscape_cookies(store: &mut CookieStore, path: &str) -> Result<usize> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read cookies file {}", path))?;
+    let now = chrono::Utc::now().timestamp();
+    let mut imported = 0;
+
+    for line in text.lines() {
+        let (line, http_only) = match line.strip_prefix("#HttpOnly_") {
+            Some(rest) => (rest, true),
+            None => (line, false),
+        };
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() < 7 {
+            tracing::warn!("Skipping malformed cookies.txt line in {}", path);
+            continue;
+        }
+        let (domain, path_attr, secure, expires, name, value) =
+            (fields[0], fields[2], fields[3], fields[4], fields[5], fields[6]);
+
+        let mut cookie = format!("{}={}; Domain={}; Path={}", name, value, domain.trim_start_matches('.'), path_attr);
+        let expires: i64 = expires.parse().unwrap_or(0);
+        if expires > 0 {
+            if expires <= now {
+                continue;
+            }
+            cookie.push_str(&format!("; Max-Age={}", expires - now));
+        }
+        if secure.eq_ignore_ascii_case("TRUE") {
+            cookie.push_str("; Secure");
+        }
+        if http_only {
+            cookie.push_str("; HttpOnly");
+        }
+
+        let raw = RawCookie::parse(cookie)?;
+        let request_url = Url::parse(&format!("https://{}{}", domain.trim_start_matches('.'), path_attr))?;
+        if store.insert_raw(&raw, &request_url).is_ok() {
+            imported += 1;
+        }
+    }
+
+    Ok(imported)
+}
@@ -0,0 +1,60 @@
+use crate::storage::Storage;
+use anyhow::Result;
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use rusqlite::params;
+use sha1::{Digest, Sha1};
+use std::io::{Read, Write};
+
+/// Raw response bodies, zlib-compressed and keyed by the hex SHA-1 of the uncompressed
+/// bytes. Identical bodies fetched from different URLs are stored once.
+#[derive(Clone)]
+pub struct BodyStore {
+    storage: Storage,
+}
+
+pub fn body_hash(body: &[u8]) -> String {
+    hex::encode(Sha1::digest(body))
+}
+
+impl BodyStore {
+    pub fn new(storage: Storage) -> Self {
+        BodyStore { storage }
+    }
+
+    /// Stores the body unless it is already present and returns its hash.
+    pub async fn put(&self, body: &[u8]) -> Result<String> {
+        let hash = body_hash(body);
+        let conn = self.storage.pool.get().await?;
+        let exists = conn
+            .prepare("SELECT 1 FROM bodies WHERE hash = ?1")?
+            .exists(params![hash])?;
+        if !exists {
+            let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(body)?;
+            let compressed = encoder.finish()?;
+            conn.execute(
+                "INSERT OR IGNORE INTO bodies (hash, size, data) VALUES (?1, ?2, ?3)",
+                params![hash, body.len() as i64, compressed],
+            )?;
+        }
+        Ok(hash)
+    }
+
+    pub async fn get(&self, hash: &str) -> Result<Option<Vec<u8>>> {
+        let conn = self.storage.pool.get().await?;
+        let compressed: Vec<u8> = match conn.query_row(
+            "SELECT data FROM bodies WHERE hash = ?1",
+            params![hash],
+            |row| row.get(0),
+        ) {
+            Ok(data) => data,
+            Err(rusqlite::Error::QueryReturnedNoRows) => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let mut body = Vec::new();
+        ZlibDecoder::new(&compressed[..]).read_to_end(&mut body)?;
+        Ok(Some(body))
+    }
+}
@@ -0,0 +1,258 @@
+use crate::config::BudgetConfig;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::Instant;
+
+/// The budget limit that stopped a crawl.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BudgetLimit {
+    MaxPages,
+    MaxExported,
+    MaxBytes,
+    MaxDuration,
+}
+
+impl BudgetLimit {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BudgetLimit::MaxPages => "max_pages",
+            BudgetLimit::MaxExported => "max_exported",
+            BudgetLimit::MaxBytes => "max_bytes",
+            BudgetLimit::MaxDuration => "max_duration",
+        }
+    }
+}
+
+impl fmt::Display for BudgetLimit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Counts what a crawl has used of its budget. A scope's tracker forwards everything to
+/// the run's tracker, so a check fails as soon as either budget is spent.
+pub struct BudgetTracker {
+    limits: BudgetConfig,
+    started: Instant,
+    pages: AtomicU64,
+    exported: AtomicU64,
+    bytes: AtomicU64,
+    exhausted: OnceLock<BudgetLimit>,
+    parent: Option<Arc<BudgetTracker>>,
+}
+
+impl BudgetTracker {
+    pub fn new(limits: BudgetConfig) -> Self {
+        BudgetTracker {
+            limits,
+            started: Instant::now(),
+            pages: AtomicU64::new(0),
+            exported: AtomicU64::new(0),
+            bytes: AtomicU64::new(0),
+            exhausted: OnceLock::new(),
+            parent: None,
+        }
+    }
+
+    /// A tracker for `limits` that also counts against `parent`.
+    pub fn child(parent: Arc<BudgetTracker>, limits: BudgetConfig) -> Self {
+        BudgetTracker {
+            parent: Some(parent),
+            ..BudgetTracker::new(limits)
+        }
+    }
+
+    pub fn add_page(&self) {
+        self.pages.fetch_add(1, Ordering::Relaxed);
+        if let Some(parent) = &self.parent {
+            parent.add_page();
+        }
+    }
+
+    pub fn add_exported(&self) {
+        self.exported.fetch_add(1, Ordering::Relaxed);
+        if let Some(parent) = &self.parent {
+            parent.add_exported();
+        }
+    }
+
+    pub fn add_bytes(&self, bytes: u64) {
+        self.bytes.fetch_add(bytes, Ordering::Relaxed);
+        if let Some(parent) = &self.parent {
+            parent.add_bytes(bytes);
+        }
+    }
+
+    /// The first limit reached by this budget, ignoring the parent's.
+    pub fn exhausted_limit(&self) -> Option<BudgetLimit> {
+        if let Some(limit) = self.exhausted.get() {
+            return Some(*limit);
+        }
+        let reached = |used: &AtomicU64, max: Option<u64>| max.is_some_and(|max| used.load(Ordering::Relaxed) >= max);
+        let limit = if reached(&self.pages, self.limits.max_pages) {
+            BudgetLimit::MaxPages
+        } else if reached(&self.exported, self.limits.max_exported) {
+            BudgetLimit::MaxExported
+        } else if reached(&self.bytes, self.limits.max_bytes) {
+            BudgetLimit::MaxBytes
+        } else if self.limits.max_duration.is_some_and(|max| self.started.elapsed() >= max) {
+            BudgetLimit::MaxDuration
+        } else {
+            return None;
+        };
+        Some(*self.exhausted.get_or_init(|| limit))
+    }
+
+    /// True once this budget or the parent's is spent; no further requests should start.
+    pub fn is_exhausted(&self) -> bool {
+        self.exhausted_limit().is_some() || self.parent.as_ref().is_some_and(|p| p.is_exhausted())
+    }
+
+    fn own_remaining_pages(&self) -> Option<u64> {
+        self.limits
+            .max_pages
+            .map(|max| max.saturating_sub(self.pages.load(Ordering::Relaxed)))
+    }
+
+    /// Pages that may still be fetched, if either budget limits them.
+    pub fn remaining_pages(&self) -> Option<u64> {
+        let parent = self.parent.as_ref().and_then(|p| p.remaining_pages());
+        match (self.own_remaining_pages(), parent) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        }
+    }
+
+    /// Pages that may still be exported, if either budget limits them.
+    pub fn remaining_exported(&self) -> Option<u64> {
+        let own = self
+            .limits
+            .max_exported
+            .map(|max| max.saturating_sub(self.exported.load(Ordering::Relaxed)));
+        let parent = self.parent.as_ref().and_then(|p| p.remaining_exported());
+        match (own, parent) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        }
+    }
+
+    /// Records that the page limit cut the crawl short, on whichever budget allowed the
+    /// fewest pages. Needed when failed fetches left the page count below the limit.
+    pub fn exhaust_pages(&self) {
+        let own = self.own_remaining_pages();
+        match (&self.parent, self.parent.as_ref().and_then(|p| p.remaining_pages())) {
+            (Some(parent), Some(theirs)) if own.is_none_or(|own| theirs <= own) => parent.exhaust_pages(),
+            _ => {
+                let _ = self.exhausted.set(BudgetLimit::MaxPages);
+            }
+        }
+    }
+
+    /// Whether links `depth` hops from the seed may be followed.
+    pub fn allows_depth(&self, depth: u32) -> bool {
+        self.limits.max_depth.is_none_or(|max| depth <= max)
+            && self.parent.as_ref().is_none_or(|p| p.allows_depth(depth))
+    }
+}
+
+/// Parses a byte count such as `500000`, `64KB`, `10MB` or `2GB` (powers of 1024).
+pub fn parse_bytes(value: &str) -> Result<u64, String> {
+    let invalid = || format!("invalid size '{}', expected e.g. 500000, 64KB, 10MB or 2GB", value);
+    let trimmed = value.trim();
+    let split = trimmed.find(|c: char| !c.is_ascii_digit()).unwrap_or(trimmed.len());
+    let (number, unit) = trimmed.split_at(split);
+    let multiplier: u64 = match unit.trim().to_uppercase().as_str() {
+        "" | "B" => 1,
+        "K" | "KB" | "KIB" => 1 << 10,
+        "M" | "MB" | "MIB" => 1 << 20,
+        "G" | "GB" | "GIB" => 1 << 30,
+        _ => return Err(invalid()),
+    };
+    number
+        .parse::<u64>()
+        .ok()
+        .and_then(|n| n.checked_mul(multiplier))
+        .ok_or_else(invalid)
+}
+
+/// Names of the limits accepted by `set_limit`.
+pub const LIMITS: [&str; 5] = ["max_pages", "max_exported", "max_depth", "max_bytes", "max_duration"];
+
+/// Sets the limit called `name` from its command-line form; `none` removes it.
+pub fn set_limit(budget: &mut BudgetConfig, name: &str, value: &str) -> Result<(), String> {
+    let value = Some(value.trim()).filter(|v| !v.eq_ignore_ascii_case("none"));
+    let count = |v: &str| v.parse::<u64>().map_err(|_| format!("invalid count '{}'", v));
+    match name {
+        "max_pages" => budget.max_pages = value.map(count).transpose()?,
+        "max_exported" => budget.max_exported = value.map(count).transpose()?,
+        "max_depth" => {
+            budget.max_depth = value
+                .map(|v| v.parse::<u32>().map_err(|_| format!("invalid depth '{}'", v)))
+                .transpose()?
+        }
+        "max_bytes" => budget.max_bytes = value.map(parse_bytes).transpose()?,
+        "max_duration" => {
+            budget.max_duration = value
+                .map(|v| humantime::parse_duration(v).map_err(|e| format!("invalid duration '{}': {}", v, e)))
+                .transpose()?
+        }
+        _ => return Err(format!("unknown limit '{}', expected one of {}", name, LIMITS.join(", "))),
+    }
+    Ok(())
+}
+
+/// The limit `name` in the form `set_limit` accepts, or `None` when it is unset.
+pub fn limit_value(budget: &BudgetConfig, name: &str) -> Option<String> {
+    match name {
+        "max_pages" => budget.max_pages.map(|max| max.to_string()),
+        "max_exported" => budget.max_exported.map(|max| max.to_string()),
+        "max_depth" => budget.max_depth.map(|max| max.to_string()),
+        "max_bytes" => budget.max_bytes.map(|max| max.to_string()),
+        "max_duration" => budget.max_duration.map(|max| humantime::format_duration(max).to_string()),
+        _ => None,
+    }
+}
+
+/// Short description of the set limits, e.g. `pages 100, depth 3`.
+pub fn describe(budget: &BudgetConfig) -> String {
+    let mut parts = Vec::new();
+    if let Some(max) = budget.max_pages {
+        parts.push(format!("pages {}", max));
+    }
+    if let Some(max) = budget.max_exported {
+        parts.push(format!("exported {}", max));
+    }
+    if let Some(max) = budget.max_depth {
+        parts.push(format!("depth {}", max));
+    }
+    if let Some(max) = budget.max_bytes {
+        parts.push(format!("bytes {}", max));
+    }
+    if let Some(max) = budget.max_duration {
+        parts.push(format!("duration {}", humantime::format_duration(max)));
+    }
+    parts.join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_byte_counts_with_binary_units() {
+        assert_eq!(parse_bytes("500000"), Ok(500_000));
+        assert_eq!(parse_bytes("64KB"), Ok(64 * 1024));
+        assert_eq!(parse_bytes("10mb"), Ok(10 * 1024 * 1024));
+        assert_eq!(parse_bytes(" 2 GiB "), Ok(2 * 1024 * 1024 * 1024));
+        assert_eq!(parse_bytes("7B"), Ok(7));
+        assert_eq!(parse_bytes("3k"), Ok(3 * 1024));
+    }
+
+    #[test]
+    fn rejects_invalid_byte_counts() {
+        for value in ["", "MB", "10TB", "1.5GB", "-5", "ten", "18446744073709551615GB"] {
+            assert!(parse_bytes(value).is_err(), "{:?} should be rejected", value);
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use crate::config::{CassetteConfig, CassetteMode};
+use crate::error::CrawlifyError;
+use crate::http::FetchedResponse;
+use chrono::{DateTime, Utc};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::{Method, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use url::Url;
+use xxhash_rust::xxh3::xxh3_64;
+
+/// One recorded exchange. The body lives next to it in `<key>.body`.
+#[derive(Debug, Serialize, Deserialize)]
+struct CassetteEntry {
+    method: String,
+    url: String,
+    request_headers: Vec<(String, String)>,
+    final_url: String,
+    status: u16,
+    headers: Vec<(String, String)>,
+    recorded_at: DateTime<Utc>,
+}
+
+/// Directory of recorded HTTP exchanges, keyed by method and URL.
+/// In record mode every live response is written out; in replay mode responses are
+/// served only from the directory and nothing touches the network.
+pub struct Cassette {
+    dir: PathBuf,
+    mode: CassetteMode,
+}
+
+fn header_pairs(headers: &HeaderMap) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .filter_map(|(k, v)| v.to_str().ok().map(|v| (k.to_string(), v.to_string())))
+        .collect()
+}
+
+impl Cassette {
+    pub fn new(config: &CassetteConfig) -> Result<Self, CrawlifyError> {
+        let dir = PathBuf::from(&config.dir);
+        match config.mode {
+            CassetteMode::Record => std::fs::create_dir_all(&dir)?,
+            CassetteMode::Replay => {
+                if !dir.is_dir() {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::NotFound,
+                        format!("cassette directory {} does not exist", config.dir),
+                    )
+                    .into());
+                }
+            }
+        }
+        Ok(Cassette {
+            dir,
+            mode: config.mode.clone(),
+        })
+    }
+
+    pub fn is_replay(&self) -> bool {
+        self.mode == CassetteMode::Replay
+    }
+
+    fn key(method: &Method, url: &Url) -> String {
+        format!("{:016x}", xxh3_64(format!("{} {}", method, url).as_bytes()))
+    }
+
+    pub async fn record(
+        &self,
+        method: &Method,
+        url: &Url,
+        request_headers: &HeaderMap,
+        response: &FetchedResponse,
+    ) -> Result<(), CrawlifyError> {
+        let key = Self::key(method, url);
+        let entry = CassetteEntry {
+            method: method.to_string(),
+            url: url.to_string(),
+            request_headers: header_pairs(request_headers),
+            final_url: response.url.to_string(),
+            status: response.status.as_u16(),
+            headers: header_pairs(&response.headers),
+            recorded_at: Utc::now(),
+        };
+        let json = serde_json::to_vec_pretty(&entry).map_err(anyhow::Error::from)?;
+        tokio::fs::write(self.dir.join(format!("{}.body", key)), &response.body).await?;
+        tokio::fs::write(self.dir.join(format!("{}.json", key)), json).await?;
+        Ok(())
+    }
+
+    pub async fn replay(&self, method: &Method, url: &Url) -> Result<Option<FetchedResponse>, CrawlifyError> {
+        let key = Self::key(method, url);
+        let json = match tokio::fs::read(self.dir.join(format!("{}.json", key))).await {
+            Ok(json) => json,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let entry: CassetteEntry = serde_json::from_slice(&json).map_err(anyhow::Error::from)?;
+        let body = tokio::fs::read(self.dir.join(format!("{}.body", key))).await?;
+
+        let mut headers = HeaderMap::new();
+        for (name, value) in &entry.headers {
+            if let (Ok(name), Ok(value)) = (HeaderName::from_bytes(name.as_bytes()), HeaderValue::from_str(value)) {
+                headers.append(name, value);
+            }
+        }
+
+        Ok(Some(FetchedResponse {
+            url: Url::parse(&entry.final_url)?,
+            status: StatusCode::from_u16(entry.status).map_err(anyhow::Error::from)?,
+            headers,
+            body: body.into(),
+            fetched_at: entry.recorded_at,
+            warc: None,
+            redirects: Vec::new(),
+        }))
+    }
+}
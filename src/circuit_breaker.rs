@@ -0,0 +1,154 @@
+use crate::config::CircuitBreakerConfig;
+use dashmap::DashMap;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CircuitState {
+    Closed,
+    /// Refusing requests until the cool-down ends.
+    Open { until: Instant },
+    /// One probe request is in flight.
+    HalfOpen,
+    Dead,
+}
+
+struct HostCircuit {
+    state: CircuitState,
+    consecutive_failures: u32,
+    /// Trips since the host last answered successfully.
+    trips: u32,
+}
+
+impl Default for HostCircuit {
+    fn default() -> Self {
+        HostCircuit {
+            state: CircuitState::Closed,
+            consecutive_failures: 0,
+            trips: 0,
+        }
+    }
+}
+
+/// Whether a request to a host may go out now.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Admission {
+    Allowed,
+    /// The host is cooling down or being probed; try again at the given time.
+    Paused(Instant),
+    Dead,
+}
+
+/// What a recorded outcome did to the host's circuit.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Transition {
+    None,
+    Tripped,
+    Recovered,
+    Died,
+}
+
+/// Per-host circuit breaker shared by all requests of a crawl, so a host that times out
+/// stops taking concurrency slots instead of burning retries on every queued URL.
+pub struct CircuitBreaker {
+    enabled: bool,
+    failure_threshold: u32,
+    cooldown: Duration,
+    max_trips: u32,
+    hosts: DashMap<String, HostCircuit>,
+}
+
+impl CircuitBreaker {
+    pub fn new(config: &CircuitBreakerConfig) -> Self {
+        CircuitBreaker {
+            enabled: config.enabled,
+            failure_threshold: config.failure_threshold.max(1),
+            cooldown: config.cooldown,
+            max_trips: config.max_trips.max(1),
+            hosts: DashMap::new(),
+        }
+    }
+
+    /// Checks whether a request to `host` may be sent. The first caller after a cool-down
+    /// becomes the probe; everyone else stays paused until the probe has been recorded.
+    pub fn admit(&self, host: &str) -> Admission {
+        if !self.enabled {
+            return Admission::Allowed;
+        }
+        let Some(mut circuit) = self.hosts.get_mut(host) else {
+            return Admission::Allowed;
+        };
+        let now = Instant::now();
+        match circuit.state {
+            CircuitState::Closed => Admission::Allowed,
+            CircuitState::Open { until } if until <= now => {
+                circuit.state = CircuitState::HalfOpen;
+                tracing::info!("Probing {} after its cool-down", host);
+                Admission::Allowed
+            }
+            CircuitState::Open { until } => Admission::Paused(until),
+            CircuitState::HalfOpen => Admission::Paused(now + Duration::from_secs(1).min(self.cooldown)),
+            CircuitState::Dead => Admission::Dead,
+        }
+    }
+
+    /// Records the outcome of a request to `host`.
+    pub fn record(&self, host: &str, success: bool) -> Transition {
+        if !self.enabled {
+            return Transition::None;
+        }
+        let mut circuit = self.hosts.entry(host.to_string()).or_default();
+        if circuit.state == CircuitState::Dead {
+            return Transition::None;
+        }
+
+        if success {
+            let recovered = circuit.state != CircuitState::Closed;
+            *circuit = HostCircuit::default();
+            if recovered {
+                tracing::info!("{} answered its probe, resuming", host);
+                return Transition::Recovered;
+            }
+            return Transition::None;
+        }
+
+        circuit.consecutive_failures += 1;
+        let trip = match circuit.state {
+            CircuitState::HalfOpen => true,
+            CircuitState::Closed => circuit.consecutive_failures >= self.failure_threshold,
+            // Requests that were already in flight when the circuit opened.
+            CircuitState::Open { .. } | CircuitState::Dead => false,
+        };
+        if !trip {
+            return Transition::None;
+        }
+
+        circuit.trips += 1;
+        if circuit.trips >= self.max_trips {
+            circuit.state = CircuitState::Dead;
+            tracing::warn!("Marking {} dead after {} failed cool-downs", host, circuit.trips);
+            return Transition::Died;
+        }
+        circuit.state = CircuitState::Open {
+            until: Instant::now() + self.cooldown,
+        };
+        tracing::warn!(
+            "Pausing {} for {:?} after {} consecutive failures",
+            host,
+            self.cooldown,
+            circuit.consecutive_failures
+        );
+        Transition::Tripped
+    }
+
+    /// Hosts marked dead so far, sorted.
+    pub fn dead_hosts(&self) -> Vec<String> {
+        let mut hosts: Vec<String> = self
+            .hosts
+            .iter()
+            .filter(|entry| entry.state == CircuitState::Dead)
+            .map(|entry| entry.key().clone())
+            .collect();
+        hosts.sort();
+        hosts
+    }
+}
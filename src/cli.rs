@@ -12,9 +12,123 @@ pub enum Commands {
     /// Initializes the database
     Init,
     /// Starts the crawler
-    Crawl,
-    /// Lists all crawl scopes
-    Scopes,
+    Crawl {
+        /// Record every fetched response to this cassette directory
+        #[arg(long, conflicts_with = "replay")]
+        record: Option<String>,
+        /// Replay responses from this cassette directory instead of the network
+        #[arg(long)]
+        replay: Option<String>,
+        /// Resolve HOST to ADDR (IP or IP:port) instead of using DNS; repeatable
+        #[arg(long, value_name = "HOST=ADDR")]
+        resolve: Vec<String>,
+        /// Stop after fetching this many pages for export
+        #[arg(long)]
+        max_pages: Option<u64>,
+        /// Stop after exporting this many pages
+        #[arg(long)]
+        max_exported: Option<u64>,
+        /// Follow links at most this many hops from the seed URL
+        #[arg(long)]
+        max_depth: Option<u32>,
+        /// Stop after downloading this much, e.g. 500MB
+        #[arg(long, value_parser = crate::budget::parse_bytes)]
+        max_bytes: Option<u64>,
+        /// Stop after running this long, e.g. 30m
+        #[arg(long, value_parser = humantime::parse_duration)]
+        max_duration: Option<std::time::Duration>,
+    },
+    /// Keeps running and crawls each scope when its schedule is due
+    Daemon {
+        /// How often to check the schedules
+        #[arg(long, default_value = "30s", value_parser = humantime::parse_duration)]
+        poll: std::time::Duration,
+        /// Resolve HOST to ADDR (IP or IP:port) instead of using DNS; repeatable
+        #[arg(long, value_name = "HOST=ADDR")]
+        resolve: Vec<String>,
+    },
+    /// Fetches stored pages again once their change rate says they are due
+    Revisit {
+        /// List the due pages without fetching them
+        #[arg(long)]
+        dry_run: bool,
+        /// Resolve HOST to ADDR (IP or IP:port) instead of using DNS; repeatable
+        #[arg(long, value_name = "HOST=ADDR")]
+        resolve: Vec<String>,
+    },
+    /// Runs the response records of WARC files through the crawl pipeline
+    #[command(name = "import-warc")]
+    ImportWarc {
+        /// WARC files to import (.warc or .warc.gz)
+        #[arg(required = true)]
+        files: Vec<String>,
+    },
+    /// Re-runs parsing, NLP filtering and export over stored response bodies
+    Reprocess,
+    /// Lists the stored text versions of a page
+    History {
+        /// URL of the page
+        url: String,
+    },
+    /// Shows a unified diff between two versions of a page (defaults to the last two)
+    Diff {
+        /// URL of the page
+        url: String,
+        /// Version number to diff from, as listed by `history`
+        from: Option<usize>,
+        /// Version number to diff to
+        to: Option<usize>,
+    },
+    /// Summarizes crawl failures by kind and host
+    Errors {
+        /// Only show failures of this crawl run
+        #[arg(long)]
+        run: Option<i64>,
+    },
+    /// Lists past crawl runs with their page counts, or compares two runs
+    Runs {
+        /// Number of runs to list
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+        /// Compare two runs side by side
+        #[arg(long, num_args = 2, value_names = ["RUN_A", "RUN_B"])]
+        compare: Option<Vec<i64>>,
+    },
+    /// Lists recorded crawl events, newest first
+    Events {
+        /// Minimum level: debug, info, warn or error
+        #[arg(long)]
+        level: Option<String>,
+        /// Only events after this time: a duration ago (e.g. 2h) or a date/RFC 3339 timestamp
+        #[arg(long)]
+        since: Option<String>,
+        /// Only events before this time, in the same formats as --since
+        #[arg(long)]
+        until: Option<String>,
+        /// Only events whose message or context contains this text
+        #[arg(long)]
+        grep: Option<String>,
+        /// Maximum number of events to show
+        #[arg(long, default_value_t = 100)]
+        limit: usize,
+    },
+    /// Lists URLs that were given up on after repeated fetch failures
+    #[command(name = "dead-letters")]
+    DeadLetters,
+    /// Puts dead-lettered URLs back into the retry queue for the next crawl
+    Retry {
+        /// IDs as listed by `dead-letters`
+        #[arg(required_unless_present = "all", conflicts_with = "all")]
+        ids: Vec<i64>,
+        /// Retry every dead-lettered URL
+        #[arg(long)]
+        all: bool,
+    },
+    /// Lists all crawl scopes with their effective settings
+    Scopes {
+        #[command(subcommand)]
+        command: Option<ScopesCommand>,
+    },
     /// Adds a new crawl scope
     Add {
         /// URL pattern to add (e.g., https://example.com/*)
@@ -26,13 +140,39 @@ pub enum Commands {
         /// ID of the scope to remove
         id: i64,
     },
+    /// Includes a crawl scope in crawls again
+    Enable {
+        /// ID of the scope to enable
+        id: i64,
+    },
+    /// Skips a crawl scope in crawls without removing it
+    Disable {
+        /// ID of the scope to disable
+        id: i64,
+    },
     /// Sets properties of a crawl scope
     Set {
         /// ID of the scope to modify
         id: i64,
-        /// Property to set (method)
+        /// Property to set: method, pattern, a setting (keywords, request_delay, concurrency,
+        /// export_path, pdf_dir, schedule) or a budget limit (max_pages, max_exported,
+        /// max_depth, max_bytes, max_duration). A schedule is an interval such as 6h or a
+        /// cron expression such as "0 3 * * *"
         property: String,
-        /// Value to set
+        /// Value to set; `none` clears a setting or budget limit
         value: String,
     },
-}
\ No newline at end of file
+}
+
+#[derive(Subcommand)]
+pub enum ScopesCommand {
+    /// Creates, updates and deactivates scopes to match `scopes:` in config.yaml
+    Sync {
+        /// Only show the changes
+        #[arg(long, conflicts_with = "yes")]
+        dry_run: bool,
+        /// Apply the changes without asking
+        #[arg(long, short)]
+        yes: bool,
+    },
+}
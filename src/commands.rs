@@ -1,9 +1,28 @@
+use crate::body_store::BodyStore;
+use crate::budget;
 use crate::config::Config;
 use crate::crawler::Crawler;
+use crate::daemon;
 use crate::error::CrawlifyError;
+use crate::exporter::JsonlExporter;
+use crate::freshness;
+use crate::http::FetchedResponse;
+use crate::pipeline::{PageOutcome, Pipeline};
+use crate::scope_config::{self, ScopeConfig};
+use crate::scope_sync;
+use crate::shutdown;
+use crate::storage::models::{CrawlRun, DeadLetter, Event, EventFilter, EventLevel, FetchError, Page, PageVersion, Scope};
 use crate::storage::Storage;
+use crate::warc::{self, WarcReader, WarcRecordRef};
+use chrono::{DateTime, Utc};
+use reqwest::header::{HeaderMap, HeaderValue, CONTENT_TYPE, ETAG, LAST_MODIFIED};
+use reqwest::StatusCode;
+use similar::TextDiff;
 use comfy_table::{Cell, Table};
+use std::io::Write;
+use std::path::Path;
 use tokio::time::Instant;
+use url::Url;
 
 pub async fn init(config: Config) -> Result<(), CrawlifyError> {
     let storage = Storage::new(&config.db_path).await?;
@@ -20,13 +39,533 @@ pub async fn crawl(config: Config) -> Result<(), CrawlifyError> {
         return Ok(());
     }
 
-    let mut crawler = Crawler::new(config).await?;
+    let mut crawler = Crawler::new(config).await?.with_shutdown(shutdown::listen());
     let start = Instant::now();
+    let mut result = Ok(());
     for scope in scopes {
-        crawler.run(&scope.pattern).await?;
+        result = crawler.run(&scope.pattern).await;
+        if result.is_err() || crawler.is_interrupted() || crawler.budget_limit().is_some() {
+            break;
+        }
+    }
+    crawler.finish(result.as_ref().err()).await?;
+    result?;
+    let duration = start.elapsed();
+    if crawler.is_interrupted() {
+        println!("Crawl interrupted after {}; the next crawl resumes from the checkpoint", humantime::format_duration(duration));
+    } else if let Some(limit) = crawler.budget_limit() {
+        println!("Crawl stopped by the {} budget after {}", limit, humantime::format_duration(duration));
+    } else {
+        println!("Crawl finished in {}", humantime::format_duration(duration));
+    }
+    Ok(())
+}
+
+pub async fn daemon(config: Config, poll: std::time::Duration) -> Result<(), CrawlifyError> {
+    daemon::run(config, poll).await
+}
+
+pub async fn revisit(config: Config, dry_run: bool) -> Result<(), CrawlifyError> {
+    let storage = Storage::new(&config.db_path).await?;
+    storage.init().await?;
+    let scopes = storage.get_active_scopes().await?;
+    let conn = storage.pool.get().await?;
+    if !dry_run {
+        freshness::backfill(&conn, &config.revisit)?;
+    }
+    let due = freshness::due(&conn, &config.revisit, &scopes)?;
+    drop(conn);
+    if due.is_empty() {
+        println!("No pages due for a revisit");
+        return Ok(());
+    }
+
+    if dry_run {
+        let now = Utc::now();
+        let mut table = Table::new();
+        table.set_header(vec!["URL", "Scope", "Checks", "Changes", "Interval", "Overdue By"]);
+        for (pattern, page, page_freshness) in &due {
+            let interval = freshness::revisit_interval(page_freshness, &config.revisit);
+            let overdue = (now - page_freshness.next_visit_at).to_std().unwrap_or_default();
+            table.add_row(vec![
+                Cell::new(&page.url),
+                Cell::new(pattern),
+                Cell::new(page_freshness.checks),
+                Cell::new(page_freshness.changes),
+                Cell::new(humantime::format_duration(std::time::Duration::from_secs(interval.as_secs()))),
+                Cell::new(humantime::format_duration(std::time::Duration::from_secs(overdue.as_secs()))),
+            ]);
+        }
+        println!("{table}");
+        println!("{} page(s) due for a revisit", due.len());
+        return Ok(());
     }
+
+    let groups = freshness::by_scope(&due);
+    let patterns = groups.iter().map(|(pattern, _)| pattern.clone()).collect();
+    let mut crawler = Crawler::for_scopes(config, patterns).await?.with_shutdown(shutdown::listen());
+    let start = Instant::now();
+    let result = freshness::revisit(&mut crawler, groups).await;
+    crawler.finish(result.as_ref().err()).await?;
+    result?;
     let duration = start.elapsed();
-    println!("Crawl finished in {}", humantime::format_duration(duration));
+    if crawler.is_interrupted() {
+        println!("Revisit interrupted after {}", humantime::format_duration(duration));
+    } else if let Some(limit) = crawler.budget_limit() {
+        println!("Revisit stopped by the {} budget after {}", limit, humantime::format_duration(duration));
+    } else {
+        println!("Revisited {} page(s) in {}", due.len(), humantime::format_duration(duration));
+    }
+    Ok(())
+}
+
+pub async fn import_warc(config: Config, files: Vec<String>) -> Result<(), CrawlifyError> {
+    let storage = Storage::new(&config.db_path).await?;
+    storage.init().await?;
+    let scopes = storage.get_active_scopes().await?;
+    let exporter = JsonlExporter::new(&config.export_path).await?;
+    let pipeline = Pipeline::new(&config, storage, exporter, None)?;
+
+    let (mut records, mut exported, mut duplicates, mut no_match, mut skipped) = (0, 0, 0, 0, 0);
+    for file in &files {
+        let mut reader = WarcReader::open(Path::new(file))?;
+        while let Some(record) = reader.next_record()? {
+            if record.header("WARC-Type") != Some("response") {
+                continue;
+            }
+            let Some(target) = record.header("WARC-Target-URI") else {
+                continue;
+            };
+            let Ok(url) = Url::parse(target.trim_matches(|c| c == '<' || c == '>')) else {
+                tracing::warn!("Skipping record with invalid target URI {} in {}", target, file);
+                continue;
+            };
+            let Some((status, headers, body)) = warc::parse_http_response(&record.block) else {
+                tracing::warn!("Skipping unparseable response record for {} in {}", url, file);
+                continue;
+            };
+            let fetched_at = record
+                .header("WARC-Date")
+                .and_then(|d| DateTime::parse_from_rfc3339(d).ok())
+                .map(|d| d.with_timezone(&Utc))
+                .unwrap_or_else(Utc::now);
+            let response = FetchedResponse {
+                url: url.clone(),
+                status,
+                headers,
+                body: body.into(),
+                fetched_at,
+                warc: Some(WarcRecordRef {
+                    filename: file.clone(),
+                    offset: record.offset,
+                }),
+                redirects: Vec::new(),
+            };
+
+            let content_rules = scopes
+                .iter()
+                .find(|s| s.matches(url.as_str()))
+                .map(|s| config.content_rules_for(&s.pattern))
+                .unwrap_or(&config.content);
+
+            records += 1;
+            match pipeline.process(&url, &response, content_rules).await {
+                Ok(PageOutcome::Exported(_)) => exported += 1,
+                Ok(PageOutcome::Duplicate(_)) => duplicates += 1,
+                Ok(PageOutcome::NoMatch) => no_match += 1,
+                Ok(PageOutcome::Skipped | PageOutcome::NotModified | PageOutcome::Recorded(_)) => skipped += 1,
+                Err(e) => tracing::error!("Failed to import {}: {}", url, e),
+            }
+        }
+    }
+
+    println!(
+        "Imported {} response records from {} files: {} exported, {} duplicates, {} filtered, {} skipped",
+        records,
+        files.len(),
+        exported,
+        duplicates,
+        no_match,
+        skipped
+    );
+    Ok(())
+}
+
+pub async fn reprocess(mut config: Config) -> Result<(), CrawlifyError> {
+    // Keep the body link on every page the pipeline rewrites.
+    config.store_bodies = true;
+    let storage = Storage::new(&config.db_path).await?;
+    storage.init().await?;
+    let scopes = storage.get_active_scopes().await?;
+    let pages = storage.get_all_pages().await?;
+    let body_store = BodyStore::new(storage.clone());
+    let exporter = JsonlExporter::new(&config.export_path).await?;
+    let pipeline = Pipeline::new(&config, storage, exporter, None)?;
+
+    let (mut processed, mut exported, mut missing) = (0, 0, 0);
+    for page in pages {
+        let Some(hash) = &page.body_hash else {
+            missing += 1;
+            continue;
+        };
+        let Some(body) = body_store.get(hash).await? else {
+            tracing::warn!("Body {} for {} is missing from the body store", hash, page.url);
+            missing += 1;
+            continue;
+        };
+        let Ok(url) = Url::parse(&page.url) else {
+            continue;
+        };
+
+        let mut headers = HeaderMap::new();
+        for (name, value) in [
+            (CONTENT_TYPE, &page.content_type),
+            (ETAG, &page.etag),
+            (LAST_MODIFIED, &page.last_modified),
+        ] {
+            if let Some(value) = value.as_deref().and_then(|v| HeaderValue::from_str(v).ok()) {
+                headers.insert(name, value);
+            }
+        }
+        let response = FetchedResponse {
+            url: url.clone(),
+            status: page
+                .status_code
+                .and_then(|code| StatusCode::from_u16(code as u16).ok())
+                .unwrap_or(StatusCode::OK),
+            headers,
+            body: body.into(),
+            fetched_at: page.fetched_at,
+            warc: page.warc_filename.clone().zip(page.warc_offset).map(|(filename, offset)| WarcRecordRef {
+                filename,
+                offset: offset as u64,
+            }),
+            redirects: page
+                .redirect_chain
+                .as_deref()
+                .and_then(|chain| serde_json::from_str(chain).ok())
+                .unwrap_or_default(),
+        };
+
+        let content_rules = scopes
+            .iter()
+            .find(|s| s.matches(url.as_str()))
+            .map(|s| config.content_rules_for(&s.pattern))
+            .unwrap_or(&config.content);
+
+        processed += 1;
+        match pipeline.process(&url, &response, content_rules).await {
+            Ok(PageOutcome::Exported(_)) => exported += 1,
+            Ok(_) => {}
+            Err(e) => tracing::error!("Failed to reprocess {}: {}", url, e),
+        }
+    }
+
+    println!(
+        "Reprocessed {} pages, {} exported, {} without a stored body",
+        processed, exported, missing
+    );
+    Ok(())
+}
+
+pub async fn history(config: Config, url: String) -> Result<(), CrawlifyError> {
+    let storage = Storage::new(&config.db_path).await?;
+    storage.init().await?;
+    let conn = storage.pool.get().await?;
+    let Some(page) = Page::find_by_url_or_alias(&conn, &url)? else {
+        println!("No page found for {}", url);
+        return Ok(());
+    };
+    let versions = PageVersion::for_page(&conn, page.id)?;
+
+    let mut table = Table::new();
+    table.set_header(vec!["Version", "Fetched At", "Title", "Text Hash", "Length"]);
+    for (index, version) in versions.iter().enumerate() {
+        table.add_row(vec![
+            Cell::new(index + 1),
+            Cell::new(version.fetched_at.to_rfc2822()),
+            Cell::new(version.title.clone().unwrap_or_default()),
+            Cell::new(&version.text_hash),
+            Cell::new(version.text.len()),
+        ]);
+    }
+
+    println!("{table}");
+    Ok(())
+}
+
+pub async fn diff(config: Config, url: String, from: Option<usize>, to: Option<usize>) -> Result<(), CrawlifyError> {
+    let storage = Storage::new(&config.db_path).await?;
+    storage.init().await?;
+    let conn = storage.pool.get().await?;
+    let Some(page) = Page::find_by_url_or_alias(&conn, &url)? else {
+        println!("No page found for {}", url);
+        return Ok(());
+    };
+    let versions = PageVersion::for_page(&conn, page.id)?;
+    if versions.len() < 2 && (from.is_none() || to.is_none()) {
+        println!("{} has {} version(s), nothing to diff", url, versions.len());
+        return Ok(());
+    }
+
+    let to = to.unwrap_or(versions.len());
+    let from = from.unwrap_or(to.saturating_sub(1));
+    let (Some(old), Some(new)) = (
+        from.checked_sub(1).and_then(|i| versions.get(i)),
+        to.checked_sub(1).and_then(|i| versions.get(i)),
+    ) else {
+        println!("Versions must be between 1 and {}", versions.len());
+        return Ok(());
+    };
+
+    let old_header = format!("version {} ({})", from, old.fetched_at.to_rfc3339());
+    let new_header = format!("version {} ({})", to, new.fetched_at.to_rfc3339());
+    let diff = TextDiff::from_lines(&old.text, &new.text);
+    print!("{}", diff.unified_diff().context_radius(3).header(&old_header, &new_header));
+    Ok(())
+}
+
+pub async fn errors(config: Config, run: Option<i64>) -> Result<(), CrawlifyError> {
+    let storage = Storage::new(&config.db_path).await?;
+    storage.init().await?;
+    let conn = storage.pool.get().await?;
+    if let Some(run_id) = run {
+        let Ok(run) = CrawlRun::find_by_id(&conn, run_id) else {
+            println!("No crawl run found with ID: {}", run_id);
+            return Ok(());
+        };
+        println!("Run {} started {}", run.id, run.started_at.to_rfc2822());
+    }
+    let summary = FetchError::summary(&conn, run)?;
+    if summary.is_empty() {
+        println!("No errors recorded");
+        return Ok(());
+    }
+
+    let mut table = Table::new();
+    table.set_header(vec!["Kind", "Host", "Count", "Statuses", "Example URL", "Last Seen"]);
+    for row in &summary {
+        table.add_row(vec![
+            Cell::new(&row.kind),
+            Cell::new(&row.host),
+            Cell::new(row.count),
+            Cell::new(row.statuses.clone().unwrap_or_default()),
+            Cell::new(&row.example_url),
+            Cell::new(row.last_seen.to_rfc2822()),
+        ]);
+    }
+
+    println!("{table}");
+    Ok(())
+}
+
+fn format_run_duration(run: &CrawlRun) -> String {
+    run.duration()
+        .and_then(|d| d.to_std().ok())
+        .map(|d| humantime::format_duration(std::time::Duration::from_secs(d.as_secs())).to_string())
+        .unwrap_or_else(|| "-".to_string())
+}
+
+fn run_exit(run: &CrawlRun) -> String {
+    match (&run.exit_reason, &run.budget_limit) {
+        (Some(reason), Some(limit)) => format!("{} ({})", reason, limit),
+        (Some(reason), None) => reason.clone(),
+        (None, _) => "-".to_string(),
+    }
+}
+
+fn run_scopes(run: &CrawlRun) -> String {
+    run.scopes
+        .as_deref()
+        .and_then(|scopes| serde_json::from_str::<Vec<String>>(scopes).ok())
+        .map(|scopes| scopes.join("\n"))
+        .unwrap_or_default()
+}
+
+pub async fn runs(config: Config, limit: usize, compare: Option<Vec<i64>>) -> Result<(), CrawlifyError> {
+    let storage = Storage::new(&config.db_path).await?;
+    storage.init().await?;
+    let conn = storage.pool.get().await?;
+
+    if let Some(ids) = compare {
+        let mut runs = Vec::new();
+        for id in ids {
+            let Ok(run) = CrawlRun::find_by_id(&conn, id) else {
+                println!("No crawl run found with ID: {}", id);
+                return Ok(());
+            };
+            runs.push(run);
+        }
+        let (a, b) = (&runs[0], &runs[1]);
+        let (errors_a, errors_b) = (a.error_count(&conn)?, b.error_count(&conn)?);
+        let mut table = Table::new();
+        table.set_header(vec![
+            "".to_string(),
+            format!("Run {}", a.id),
+            format!("Run {}", b.id),
+            "Change".to_string(),
+        ]);
+        let text_row = |label: &str, a: String, b: String| vec![Cell::new(label), Cell::new(a), Cell::new(b), Cell::new("")];
+        let count_row = |label: &str, a: i64, b: i64| {
+            vec![Cell::new(label), Cell::new(a), Cell::new(b), Cell::new(format!("{:+}", b - a))]
+        };
+        table.add_row(text_row("Started", a.started_at.to_rfc2822(), b.started_at.to_rfc2822()));
+        table.add_row(text_row("Duration", format_run_duration(a), format_run_duration(b)));
+        table.add_row(text_row("Exit", run_exit(a), run_exit(b)));
+        table.add_row(text_row("Scopes", run_scopes(a), run_scopes(b)));
+        table.add_row(count_row("Fetched", a.pages_fetched, b.pages_fetched));
+        table.add_row(count_row("Exported", a.pages_exported, b.pages_exported));
+        table.add_row(count_row("Duplicates", a.pages_duplicate, b.pages_duplicate));
+        table.add_row(count_row("Failed", a.pages_failed, b.pages_failed));
+        table.add_row(count_row("Errors", errors_a, errors_b));
+        println!("{table}");
+        return Ok(());
+    }
+
+    let runs = CrawlRun::recent(&conn, limit)?;
+    if runs.is_empty() {
+        println!("No crawl runs recorded");
+        return Ok(());
+    }
+
+    let mut table = Table::new();
+    table.set_header(vec![
+        "ID", "Started", "Duration", "Exit", "Scopes", "Fetched", "Exported", "Duplicates", "Failed", "Errors",
+    ]);
+    for run in &runs {
+        table.add_row(vec![
+            Cell::new(run.id),
+            Cell::new(run.started_at.to_rfc2822()),
+            Cell::new(format_run_duration(run)),
+            Cell::new(run_exit(run)),
+            Cell::new(run_scopes(run)),
+            Cell::new(run.pages_fetched),
+            Cell::new(run.pages_exported),
+            Cell::new(run.pages_duplicate),
+            Cell::new(run.pages_failed),
+            Cell::new(run.error_count(&conn)?),
+        ]);
+    }
+
+    println!("{table}");
+    Ok(())
+}
+
+/// Parses a `--since`/`--until` bound: a duration ago such as `2h`, a date, or an RFC 3339 timestamp.
+fn parse_time_bound(value: &str) -> Option<DateTime<Utc>> {
+    if let Ok(ago) = humantime::parse_duration(value) {
+        return chrono::Duration::from_std(ago).ok().map(|ago| Utc::now() - ago);
+    }
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Some(time.with_timezone(&Utc));
+    }
+    chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .map(|time| time.and_utc())
+}
+
+pub async fn events(
+    config: Config,
+    level: Option<String>,
+    since: Option<String>,
+    until: Option<String>,
+    grep: Option<String>,
+    limit: usize,
+) -> Result<(), CrawlifyError> {
+    let min_level = match level {
+        Some(level) => Some(
+            EventLevel::parse(&level)
+                .ok_or_else(|| anyhow::anyhow!("unknown level '{}', expected debug, info, warn or error", level))?,
+        ),
+        None => None,
+    };
+    let bound = |value: Option<String>| match value {
+        Some(value) => parse_time_bound(&value)
+            .map(Some)
+            .ok_or_else(|| anyhow::anyhow!("invalid time '{}', expected a duration like 2h, a date or an RFC 3339 timestamp", value)),
+        None => Ok(None),
+    };
+    let filter = EventFilter {
+        min_level,
+        since: bound(since)?,
+        until: bound(until)?,
+        text: grep,
+        limit,
+    };
+
+    let storage = Storage::new(&config.db_path).await?;
+    storage.init().await?;
+    let conn = storage.pool.get().await?;
+    let events = Event::search(&conn, &filter)?;
+    if events.is_empty() {
+        println!("No events found");
+        return Ok(());
+    }
+
+    let mut table = Table::new();
+    table.set_header(vec!["ID", "Time", "Level", "Message", "Context"]);
+    for event in &events {
+        table.add_row(vec![
+            Cell::new(event.id),
+            Cell::new(event.timestamp.to_rfc2822()),
+            Cell::new(event.level.as_str()),
+            Cell::new(&event.message),
+            Cell::new(event.context.clone().unwrap_or_default()),
+        ]);
+    }
+
+    println!("{table}");
+    Ok(())
+}
+
+pub async fn dead_letters(config: Config) -> Result<(), CrawlifyError> {
+    let storage = Storage::new(&config.db_path).await?;
+    storage.init().await?;
+    let conn = storage.pool.get().await?;
+    let letters = DeadLetter::all(&conn)?;
+    if letters.is_empty() {
+        println!("No dead letters");
+        return Ok(());
+    }
+
+    let mut table = Table::new();
+    table.set_header(vec!["ID", "URL", "Tries", "Last Error", "Given Up At"]);
+    for letter in &letters {
+        table.add_row(vec![
+            Cell::new(letter.id),
+            Cell::new(&letter.url),
+            Cell::new(letter.tries),
+            Cell::new(letter.last_error.clone().unwrap_or_default()),
+            Cell::new(letter.created_at.to_rfc2822()),
+        ]);
+    }
+
+    println!("{table}");
+    Ok(())
+}
+
+pub async fn retry_dead_letters(config: Config, ids: Vec<i64>, all: bool) -> Result<(), CrawlifyError> {
+    let storage = Storage::new(&config.db_path).await?;
+    storage.init().await?;
+    let conn = storage.pool.get().await?;
+    let letters = if all {
+        DeadLetter::all(&conn)?
+    } else {
+        let mut letters = Vec::new();
+        for id in ids {
+            match DeadLetter::find_by_id(&conn, id) {
+                Ok(letter) => letters.push(letter),
+                Err(_) => println!("No dead letter found with ID: {}", id),
+            }
+        }
+        letters
+    };
+
+    for letter in &letters {
+        letter.requeue(&conn)?;
+    }
+    println!("Queued {} URLs for the next crawl", letters.len());
     Ok(())
 }
 
@@ -52,7 +591,7 @@ pub async fn list(config: Config) -> Result<(), CrawlifyError> {
             Cell::new(page.status_code.unwrap_or(0)),
             Cell::new(
                 page.text_hash
-                    .map(|h| hex::encode(h))
+                    .map(hex::encode)
                     .unwrap_or_default(),
             ),
         ]);
@@ -65,6 +604,7 @@ pub async fn list(config: Config) -> Result<(), CrawlifyError> {
 
 pub async fn add_scope(config: Config, pattern: String) -> Result<(), CrawlifyError> {
     let storage = Storage::new(&config.db_path).await?;
+    storage.init().await?;
     let conn = storage.pool.get().await?;
     
     // Determine method based on config
@@ -85,21 +625,10 @@ pub async fn add_scope(config: Config, pattern: String) -> Result<(), CrawlifyEr
 
 pub async fn list_scopes(config: Config) -> Result<(), CrawlifyError> {
     let storage = Storage::new(&config.db_path).await?;
+    storage.init().await?;
     let conn = storage.pool.get().await?;
-    
-    let mut stmt = conn.prepare(
-        "SELECT id, pattern, method, is_active, created_at FROM scopes ORDER BY id"
-    )?;
-    
-    let scope_rows = stmt.query_map(rusqlite::NO_PARAMS, |row| {
-        Ok((
-            row.get::<_, i64>(0)?,      // id
-            row.get::<_, String>(1)?,   // pattern
-            row.get::<_, String>(2)?,   // method
-            row.get::<_, bool>(3)?,     // is_active
-            row.get::<_, String>(4)?,   // created_at
-        ))
-    })?;
+    let mut scopes = Scope::get_all(&conn)?;
+    scopes.sort_by_key(|scope| scope.id);
     
     let mut table = Table::new();
     table.set_header(vec![
@@ -107,17 +636,23 @@ pub async fn list_scopes(config: Config) -> Result<(), CrawlifyError> {
         "Pattern",
         "Method",
         "Active",
+        "Settings",
         "Created At",
     ]);
     
-    for scope_result in scope_rows {
-        let (id, pattern, method, is_active, created_at) = scope_result?;
+    for scope in scopes {
+        let settings = Scope::settings(&conn, scope.id)?;
+        let effective = match ScopeConfig::resolve(&config, &scope, &settings) {
+            Ok(scope_config) => scope_config.describe(&scope).join("\n"),
+            Err(e) => format!("invalid: {}", e),
+        };
         table.add_row(vec![
-            Cell::new(id),
-            Cell::new(pattern),
-            Cell::new(method),
-            Cell::new(if is_active { "Yes" } else { "No" }),
-            Cell::new(created_at),
+            Cell::new(scope.id),
+            Cell::new(&scope.pattern),
+            Cell::new(scope.method.as_str()),
+            Cell::new(if scope.is_active { "Yes" } else { "No" }),
+            Cell::new(effective),
+            Cell::new(scope.created_at.format("%Y-%m-%d %H:%M:%S")),
         ]);
     }
     
@@ -125,8 +660,58 @@ pub async fn list_scopes(config: Config) -> Result<(), CrawlifyError> {
     Ok(())
 }
 
+pub async fn sync_scopes(config: Config, dry_run: bool, yes: bool) -> Result<(), CrawlifyError> {
+    if config.scopes.is_empty() {
+        println!("No scopes declared in config.yaml; nothing to sync");
+        return Ok(());
+    }
+    let storage = Storage::new(&config.db_path).await?;
+    storage.init().await?;
+    let conn = storage.pool.get().await?;
+
+    let changes = scope_sync::plan(&conn, &config)?;
+    if changes.is_empty() {
+        println!("Scopes are in sync with config.yaml");
+        return Ok(());
+    }
+    for change in &changes {
+        println!("{}", change);
+    }
+    if dry_run {
+        return Ok(());
+    }
+    if !yes {
+        print!("Apply {} change(s)? [y/N] ", changes.len());
+        std::io::stdout().flush()?;
+        let mut answer = String::new();
+        std::io::stdin().read_line(&mut answer)?;
+        if !matches!(answer.trim().to_lowercase().as_str(), "y" | "yes") {
+            println!("Aborted");
+            return Ok(());
+        }
+    }
+    scope_sync::apply(&conn, &changes)?;
+    println!("Applied {} change(s)", changes.len());
+    Ok(())
+}
+
+pub async fn set_scope_active(config: Config, id: i64, active: bool) -> Result<(), CrawlifyError> {
+    let storage = Storage::new(&config.db_path).await?;
+    storage.init().await?;
+    let conn = storage.pool.get().await?;
+
+    let Ok(scope) = Scope::find_by_id(&conn, id) else {
+        println!("No scope found with ID: {}", id);
+        return Ok(());
+    };
+    Scope::set_active(&conn, id, active)?;
+    println!("{} scope {}: {}", if active { "Enabled" } else { "Disabled" }, id, scope.pattern);
+    Ok(())
+}
+
 pub async fn set_scope(config: Config, id: i64, property: String, value: String) -> Result<(), CrawlifyError> {
     let storage = Storage::new(&config.db_path).await?;
+    storage.init().await?;
     let conn = storage.pool.get().await?;
     
     match property.to_lowercase().as_str() {
@@ -166,8 +751,65 @@ pub async fn set_scope(config: Config, id: i64, property: String, value: String)
                 }
             }
         }
+        limit if budget::LIMITS.contains(&limit) => {
+            let Ok(mut scope) = Scope::find_by_id(&conn, id) else {
+                println!("No scope found with ID: {}", id);
+                return Ok(());
+            };
+            if let Err(e) = budget::set_limit(&mut scope.budget, limit, &value) {
+                println!("{}", e);
+                return Ok(());
+            }
+            Scope::set_budget(&conn, id, &scope.budget)?;
+            println!("Updated scope {} {} to '{}': {}", id, limit, value, scope.pattern);
+        }
+        "pattern" => {
+            let Ok(scope) = Scope::find_by_id(&conn, id) else {
+                println!("No scope found with ID: {}", id);
+                return Ok(());
+            };
+            let pattern = value.trim();
+            if pattern.is_empty() {
+                println!("The pattern cannot be empty");
+                return Ok(());
+            }
+            if Scope::get_all(&conn)?.iter().any(|s| s.id != id && s.pattern == pattern) {
+                println!("Another scope already has the pattern '{}'", pattern);
+                return Ok(());
+            }
+            Scope::update_pattern(&conn, id, pattern)?;
+            // A checkpointed frontier holds URLs of the old pattern.
+            storage.clear_frontier_state(id).await?;
+            println!("Updated scope {} pattern: {} -> {}", id, scope.pattern, pattern);
+            if config.scope_overrides_for(&scope.pattern).is_some() {
+                println!("Note: scope_overrides in the config file still refer to '{}'", scope.pattern);
+            }
+        }
+        setting if scope_config::SETTINGS.contains(&setting) => {
+            let Ok(scope) = Scope::find_by_id(&conn, id) else {
+                println!("No scope found with ID: {}", id);
+                return Ok(());
+            };
+            if value.eq_ignore_ascii_case("none") {
+                Scope::set_setting(&conn, id, setting, None)?;
+                println!("Cleared scope {} {}: {}", id, setting, scope.pattern);
+                return Ok(());
+            }
+            match scope_config::normalize(setting, &value) {
+                Ok(value) => {
+                    Scope::set_setting(&conn, id, setting, Some(&value))?;
+                    println!("Updated scope {} {} to '{}': {}", id, setting, value, scope.pattern);
+                }
+                Err(e) => println!("{}", e),
+            }
+        }
         _ => {
-            println!("Unknown property '{}'. Supported properties: method", property);
+            println!(
+                "Unknown property '{}'. Supported properties: method, pattern, {}, {}",
+                property,
+                scope_config::SETTINGS.join(", "),
+                budget::LIMITS.join(", ")
+            );
         }
     }
     
@@ -176,6 +818,7 @@ pub async fn set_scope(config: Config, id: i64, property: String, value: String)
 
 pub async fn remove_scope(config: Config, id: i64) -> Result<(), CrawlifyError> {
     let storage = Storage::new(&config.db_path).await?;
+    storage.init().await?;
     let conn = storage.pool.get().await?;
     
     // First, check if the scope exists and get its pattern for confirmation
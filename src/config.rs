@@ -1,6 +1,169 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::IpAddr;
 use std::time::Duration;
 
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum CassetteMode {
+    Record,
+    Replay,
+}
+
+/// Records fetched responses to `dir`, or replays a crawl from it without network access.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CassetteConfig {
+    pub mode: CassetteMode,
+    pub dir: String,
+}
+
+fn default_warc_prefix() -> String {
+    "crawlify".to_string()
+}
+
+fn default_warc_max_file_size_mb() -> u64 {
+    1024
+}
+
+/// Archives all fetched traffic as gzipped WARC files in `dir`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WarcConfig {
+    pub dir: String,
+    #[serde(default = "default_warc_prefix")]
+    pub prefix: String,
+    #[serde(default = "default_warc_max_file_size_mb")]
+    pub max_file_size_mb: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ProxyRotation {
+    #[default]
+    RoundRobin,
+    Random,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_proxy_max_failures() -> u32 {
+    3
+}
+
+fn default_proxy_quarantine() -> Duration {
+    Duration::from_secs(60)
+}
+
+/// Several egress proxies used in turn. A proxy that fails `max_failures` times in a row
+/// is quarantined; once `quarantine` has passed it is re-checked against `check_url`
+/// (or simply released when no check URL is set).
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ProxyPoolConfig {
+    pub proxies: Vec<String>,
+    #[serde(default)]
+    pub rotation: ProxyRotation,
+    /// Keep sending each host through the same proxy while it stays healthy.
+    #[serde(default = "default_true")]
+    pub sticky_hosts: bool,
+    #[serde(default = "default_proxy_max_failures")]
+    pub max_failures: u32,
+    #[serde(with = "humantime_serde", default = "default_proxy_quarantine")]
+    pub quarantine: Duration,
+    pub check_url: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum HttpVersion {
+    /// HTTP/1.1, upgrading to HTTP/2 where the server offers it.
+    #[default]
+    Auto,
+    Http1Only,
+    /// Speak HTTP/2 from the first byte, e.g. to h2c servers.
+    Http2PriorKnowledge,
+}
+
+/// TLS settings for targets behind a private CA or requiring mutual TLS.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct TlsConfig {
+    /// PEM files with extra root certificates, trusted alongside the system roots.
+    #[serde(default)]
+    pub ca_certs: Vec<String>,
+    /// PEM client certificate (chain) presented for mutual TLS.
+    pub client_cert: Option<String>,
+    /// PKCS#8 PEM private key for `client_cert`.
+    pub client_key: Option<String>,
+    /// Accepts any certificate and host name. Only for test hosts.
+    #[serde(default)]
+    pub insecure: bool,
+}
+
+/// Refuses private, loopback, link-local and other special-purpose destinations.
+/// `allow` takes hostnames and IP addresses or CIDR ranges that may be crawled anyway.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SsrfConfig {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub allow: Vec<String>,
+}
+
+impl Default for SsrfConfig {
+    fn default() -> Self {
+        SsrfConfig {
+            enabled: true,
+            allow: Vec::new(),
+        }
+    }
+}
+
+fn default_failure_threshold() -> u32 {
+    5
+}
+
+fn default_circuit_cooldown() -> Duration {
+    Duration::from_secs(60)
+}
+
+fn default_max_trips() -> u32 {
+    3
+}
+
+/// Per-host circuit breaker. After `failure_threshold` failed requests in a row a host is
+/// paused for `cooldown`, then probed with a single request. A host whose probe fails
+/// `max_trips` times in a row is marked dead for the rest of the crawl.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CircuitBreakerConfig {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    #[serde(default = "default_failure_threshold")]
+    pub failure_threshold: u32,
+    #[serde(with = "humantime_serde", default = "default_circuit_cooldown")]
+    pub cooldown: Duration,
+    #[serde(default = "default_max_trips")]
+    pub max_trips: u32,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        CircuitBreakerConfig {
+            enabled: true,
+            failure_threshold: default_failure_threshold(),
+            cooldown: default_circuit_cooldown(),
+            max_trips: default_max_trips(),
+        }
+    }
+}
+
+fn default_max_redirects() -> usize {
+    10
+}
+
+fn default_dns_cache_ttl() -> Duration {
+    Duration::from_secs(300)
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct HttpConfig {
     #[serde(with = "humantime_serde")]
@@ -9,6 +172,28 @@ pub struct HttpConfig {
     pub request_timeout: Duration,
     pub pool_max_idle_per_host: usize,
     pub proxy: Option<String>,
+    /// Takes precedence over `proxy` when set.
+    pub proxy_pool: Option<ProxyPoolConfig>,
+    #[serde(default)]
+    pub tls: TlsConfig,
+    #[serde(default)]
+    pub http_version: HttpVersion,
+    /// Local IP address outbound connections are bound to.
+    pub local_address: Option<IpAddr>,
+    /// Hostname to `IP` or `IP:port`, bypassing DNS, e.g. to crawl a local mirror.
+    #[serde(default)]
+    pub host_overrides: HashMap<String, String>,
+    /// How long resolved addresses are reused; `0s` disables the cache.
+    #[serde(with = "humantime_serde", default = "default_dns_cache_ttl")]
+    pub dns_cache_ttl: Duration,
+    #[serde(default)]
+    pub ssrf: SsrfConfig,
+    #[serde(default = "default_max_redirects")]
+    pub max_redirects: usize,
+    #[serde(default)]
+    pub circuit_breaker: CircuitBreakerConfig,
+    pub cassette: Option<CassetteConfig>,
+    pub warc: Option<WarcConfig>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -17,12 +202,238 @@ pub struct NlpConfig {
     pub keywords: Vec<String>,
 }
 
+/// MIME allow/deny rules, e.g. `text/*` or `application/pdf`.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ContentConfig {
+    #[serde(default)]
+    pub allow: Vec<String>,
+    #[serde(default)]
+    pub deny: Vec<String>,
+}
+
+/// Names of the environment variables holding Basic auth credentials.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BasicAuthConfig {
+    pub username_env: String,
+    pub password_env: String,
+}
+
+/// Credentials and cookies attached to every request of a scope.
+/// Secrets are never stored in the config itself, only the environment variables holding them.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct AuthConfig {
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    pub basic: Option<BasicAuthConfig>,
+    pub bearer_token_env: Option<String>,
+    /// Netscape `cookies.txt` file imported before the crawl.
+    pub cookies_file: Option<String>,
+    /// JSON cookie jar loaded before and saved after the crawl.
+    pub cookie_jar: Option<String>,
+}
+
+fn default_username_field() -> String {
+    "username".to_string()
+}
+
+fn default_password_field() -> String {
+    "password".to_string()
+}
+
+/// Form login performed before a scope is crawled. Success is checked by `success_cookie`
+/// and/or `success_redirect`; with neither set, not landing back on `url` counts as success.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LoginConfig {
+    pub url: String,
+    /// Overrides the `action` attribute of the login form.
+    pub form_action: Option<String>,
+    #[serde(default = "default_username_field")]
+    pub username_field: String,
+    #[serde(default = "default_password_field")]
+    pub password_field: String,
+    pub username_env: String,
+    pub password_env: String,
+    #[serde(default)]
+    pub extra_fields: HashMap<String, String>,
+    pub success_cookie: Option<String>,
+    pub success_redirect: Option<String>,
+}
+
+fn default_retry_max_tries() -> u32 {
+    5
+}
+
+fn default_retry_base_delay() -> Duration {
+    Duration::from_secs(5 * 60)
+}
+
+fn default_retry_max_delay() -> Duration {
+    Duration::from_secs(6 * 60 * 60)
+}
+
+fn default_shutdown_grace() -> Duration {
+    Duration::from_secs(30)
+}
+
+/// Durable retries of failed fetches across crawls. The `n`th retry waits
+/// `base_delay * 2^(n-1)`, capped at `max_delay`; after `max_tries` failures the URL
+/// is moved to the dead-letter list.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RetryConfig {
+    #[serde(default = "default_retry_max_tries")]
+    pub max_tries: u32,
+    #[serde(with = "humantime_serde", default = "default_retry_base_delay")]
+    pub base_delay: Duration,
+    #[serde(with = "humantime_serde", default = "default_retry_max_delay")]
+    pub max_delay: Duration,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        RetryConfig {
+            max_tries: default_retry_max_tries(),
+            base_delay: default_retry_base_delay(),
+            max_delay: default_retry_max_delay(),
+        }
+    }
+}
+
+fn default_revisit_interval() -> Duration {
+    Duration::from_secs(15 * 60)
+}
+
+fn default_revisit_max_pages() -> u64 {
+    200
+}
+
+fn default_revisit_min_interval() -> Duration {
+    Duration::from_secs(60 * 60)
+}
+
+fn default_revisit_max_interval() -> Duration {
+    Duration::from_secs(30 * 24 * 60 * 60)
+}
+
+fn default_revisit_initial_interval() -> Duration {
+    Duration::from_secs(24 * 60 * 60)
+}
+
+/// Revisits of stored pages, each scheduled from the page's observed change rate and
+/// kept between `min_interval` and `max_interval`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RevisitConfig {
+    /// Lets `crawlify daemon` run a revisit pass every `interval`.
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(with = "humantime_serde", default = "default_revisit_interval")]
+    pub interval: Duration,
+    /// Pages fetched by one revisit pass at most; the most overdue go first.
+    #[serde(default = "default_revisit_max_pages")]
+    pub max_pages: u64,
+    #[serde(with = "humantime_serde", default = "default_revisit_min_interval")]
+    pub min_interval: Duration,
+    #[serde(with = "humantime_serde", default = "default_revisit_max_interval")]
+    pub max_interval: Duration,
+    /// Revisit delay of a page fetched only once so far.
+    #[serde(with = "humantime_serde", default = "default_revisit_initial_interval")]
+    pub initial_interval: Duration,
+}
+
+impl Default for RevisitConfig {
+    fn default() -> Self {
+        RevisitConfig {
+            enabled: false,
+            interval: default_revisit_interval(),
+            max_pages: default_revisit_max_pages(),
+            min_interval: default_revisit_min_interval(),
+            max_interval: default_revisit_max_interval(),
+            initial_interval: default_revisit_initial_interval(),
+        }
+    }
+}
+
+/// Limits on a crawl. Unset limits are unlimited. The top-level `budget` applies to the
+/// whole run; scopes carry their own, set with `crawlify set`.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct BudgetConfig {
+    /// Pages fetched for export.
+    pub max_pages: Option<u64>,
+    pub max_exported: Option<u64>,
+    /// Links followed from the seed URL; the seed itself is depth 0.
+    pub max_depth: Option<u32>,
+    /// Response bytes downloaded, including discovery.
+    pub max_bytes: Option<u64>,
+    #[serde(with = "humantime_serde", default)]
+    pub max_duration: Option<Duration>,
+}
+
+impl BudgetConfig {
+    pub fn is_unlimited(&self) -> bool {
+        *self == BudgetConfig::default()
+    }
+}
+
+/// Settings that apply to a single scope, keyed by its pattern in `scope_overrides`.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ScopeOverrides {
+    pub content: Option<ContentConfig>,
+    pub auth: Option<AuthConfig>,
+    pub login: Option<LoginConfig>,
+    /// Replaces `nlp.keywords` for pages of this scope.
+    pub keywords: Option<Vec<String>>,
+    /// Minimum time between two requests to the same host.
+    #[serde(with = "humantime_serde", default)]
+    pub request_delay: Option<Duration>,
+    pub concurrency: Option<usize>,
+    pub export_path: Option<String>,
+    pub pdf_dir: Option<String>,
+    /// Recrawl interval (`6h`) or cron expression (`0 3 * * *`) for `crawlify daemon`.
+    pub schedule: Option<String>,
+}
+
+/// A scope declared in the config file, created or updated by `crawlify scopes sync`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ScopeDefinition {
+    pub pattern: String,
+    /// DEFAULT, NLP, HEADERS or CHANGED; defaults as for `crawlify add`.
+    pub method: Option<String>,
+    pub keywords: Option<Vec<String>>,
+    #[serde(with = "humantime_serde", default)]
+    pub request_delay: Option<Duration>,
+    pub concurrency: Option<usize>,
+    pub export_path: Option<String>,
+    pub pdf_dir: Option<String>,
+    pub schedule: Option<String>,
+    /// Budget limits, written inline as `max_pages`, `max_depth` and so on.
+    #[serde(flatten)]
+    pub budget: BudgetConfig,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Config {
     pub db_path: String,
     pub export_path: String,
     pub http: HttpConfig,
     pub nlp: NlpConfig,
+    #[serde(default)]
+    pub content: ContentConfig,
+    #[serde(default)]
+    pub scope_overrides: HashMap<String, ScopeOverrides>,
+    /// Scopes kept in sync with the database by `crawlify scopes sync`.
+    #[serde(default)]
+    pub scopes: Vec<ScopeDefinition>,
+    /// Keep compressed raw bodies in the database so pages can be reprocessed later.
+    #[serde(default)]
+    pub store_bodies: bool,
+    #[serde(default)]
+    pub retry: RetryConfig,
+    #[serde(default)]
+    pub budget: BudgetConfig,
+    #[serde(default)]
+    pub revisit: RevisitConfig,
+    /// How long in-flight requests may run after Ctrl-C or SIGTERM before they are abandoned.
+    #[serde(with = "humantime_serde", default = "default_shutdown_grace")]
+    pub shutdown_grace: Duration,
 }
 
 impl Config {
@@ -32,6 +443,24 @@ impl Config {
         Ok(config)
     }
 
+    pub fn scope_overrides_for(&self, pattern: &str) -> Option<&ScopeOverrides> {
+        self.scope_overrides.get(pattern)
+    }
+
+    pub fn content_rules_for(&self, pattern: &str) -> &ContentConfig {
+        self.scope_overrides_for(pattern)
+            .and_then(|o| o.content.as_ref())
+            .unwrap_or(&self.content)
+    }
+
+    pub fn auth_for(&self, pattern: &str) -> Option<&AuthConfig> {
+        self.scope_overrides_for(pattern).and_then(|o| o.auth.as_ref())
+    }
+
+    pub fn login_for(&self, pattern: &str) -> Option<&LoginConfig> {
+        self.scope_overrides_for(pattern).and_then(|o| o.login.as_ref())
+    }
+
     pub fn get_concurrency(&self) -> (usize, usize) {
         // This is a placeholder for a more sophisticated concurrency model
         (32, 1)
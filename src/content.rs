@@ -0,0 +1,260 @@
+use crate::config::ContentConfig;
+use crate::error::CrawlifyError;
+use crate::parser::{self, OutlinkWithScore, PageData};
+use crate::pdf_text;
+use reqwest::header::{HeaderMap, CONTENT_TYPE};
+use url::Url;
+
+/// How a fetched body should be handled, decided from its Content-Type and magic bytes.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ContentKind {
+    Html,
+    Text,
+    Markdown,
+    Pdf,
+    Other(String),
+}
+
+const MAGIC_BYTES: [(&[u8], &str); 7] = [
+    (b"%PDF-", "application/pdf"),
+    (b"\x89PNG\r\n\x1a\n", "image/png"),
+    (b"\xFF\xD8\xFF", "image/jpeg"),
+    (b"GIF87a", "image/gif"),
+    (b"GIF89a", "image/gif"),
+    (b"PK\x03\x04", "application/zip"),
+    (b"\x1F\x8B", "application/gzip"),
+];
+
+/// Returns the bare MIME type (lowercased, without parameters) from the response headers.
+pub fn content_type(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(normalize_mime)
+        .filter(|m| !m.is_empty())
+}
+
+fn normalize_mime(value: &str) -> String {
+    value
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_lowercase()
+}
+
+/// Guesses a MIME type from the first bytes of the body.
+pub fn sniff(body: &[u8]) -> Option<&'static str> {
+    for (magic, mime) in MAGIC_BYTES {
+        if body.starts_with(magic) {
+            return Some(mime);
+        }
+    }
+
+    let head = &body[..body.len().min(512)];
+    let head = String::from_utf8_lossy(head).trim_start_matches('\u{feff}').trim_start().to_lowercase();
+    if head.starts_with("<!doctype html") || head.starts_with("<html") {
+        return Some("text/html");
+    }
+    None
+}
+
+/// Resolves the effective MIME type. Binary signatures win over the header since servers
+/// routinely label downloads as `text/html`; otherwise the header is trusted unless it is
+/// missing or a generic `application/octet-stream`.
+pub fn resolve_mime(content_type: Option<&str>, body: &[u8]) -> String {
+    let sniffed = sniff(body);
+    match (content_type, sniffed) {
+        (_, Some(mime)) if mime != "text/html" => mime.to_string(),
+        (Some(header), _) if header != "application/octet-stream" => header.to_string(),
+        (_, Some(mime)) => mime.to_string(),
+        (Some(header), None) => header.to_string(),
+        (None, None) => {
+            if std::str::from_utf8(body).is_ok() {
+                "text/plain".to_string()
+            } else {
+                "application/octet-stream".to_string()
+            }
+        }
+    }
+}
+
+pub fn classify(mime: &str) -> ContentKind {
+    match mime {
+        "text/html" | "application/xhtml+xml" => ContentKind::Html,
+        "text/markdown" | "text/x-markdown" => ContentKind::Markdown,
+        "text/plain" => ContentKind::Text,
+        "application/pdf" => ContentKind::Pdf,
+        other => ContentKind::Other(other.to_string()),
+    }
+}
+
+impl ContentConfig {
+    /// Deny rules take precedence; an empty allow list admits everything not denied.
+    /// Patterns are exact MIME types or `type/*` wildcards.
+    pub fn is_allowed(&self, mime: &str) -> bool {
+        if self.deny.iter().any(|p| mime_matches(p, mime)) {
+            return false;
+        }
+        self.allow.is_empty() || self.allow.iter().any(|p| mime_matches(p, mime))
+    }
+}
+
+fn mime_matches(pattern: &str, mime: &str) -> bool {
+    let pattern = pattern.trim().to_lowercase();
+    if pattern == "*" || pattern == "*/*" {
+        return true;
+    }
+    match pattern.strip_suffix("/*") {
+        Some(prefix) => mime.split('/').next() == Some(prefix),
+        None => pattern == mime,
+    }
+}
+
+/// Whether `dispatch` extracts text from a body of this type. Other types get an empty
+/// `PageData` and are only recorded, never filtered or exported.
+pub fn extracts_text(content_type: Option<&str>, body: &[u8]) -> bool {
+    !matches!(classify(&resolve_mime(content_type, body)), ContentKind::Other(_))
+}
+
+/// Dispatches a fetched body to the extractor for its content kind.
+/// Returns `None` when the type is denied by `rules`, and an error when extraction fails.
+pub async fn dispatch(
+    content_type: Option<&str>,
+    body: &[u8],
+    url: &Url,
+    rules: &ContentConfig,
+) -> Result<Option<PageData>, CrawlifyError> {
+    let mime = resolve_mime(content_type, body);
+    if !rules.is_allowed(&mime) {
+        tracing::debug!("Skipping {} ({}): denied by content rules", url, mime);
+        return Ok(None);
+    }
+
+    match classify(&mime) {
+        ContentKind::Html => Ok(Some(parser::parse(body, url))),
+        ContentKind::Text => Ok(Some(parse_text(body, url, false))),
+        ContentKind::Markdown => Ok(Some(parse_text(body, url, true))),
+        ContentKind::Pdf => parse_pdf(body, url).await.map(Some),
+        ContentKind::Other(mime) => {
+            tracing::info!("Recording {} without content extraction ({})", url, mime);
+            Ok(Some(empty_page_data()))
+        }
+    }
+}
+
+fn empty_page_data() -> PageData {
+    PageData {
+        title: None,
+        canonical_url: None,
+        outlinks: Vec::new(),
+        outlinks_with_scores: Vec::new(),
+        structured_data: serde_json::Value::Object(serde_json::Map::new()),
+        main_content: String::new(),
+    }
+}
+
+/// PDF extraction is CPU-bound, so it runs on the blocking pool rather than an executor
+/// thread.
+async fn parse_pdf(body: &[u8], url: &Url) -> Result<PageData, CrawlifyError> {
+    let body = body.to_vec();
+    let pdf = tokio::task::spawn_blocking(move || pdf_text::extract(&body))
+        .await
+        .map_err(anyhow::Error::from)
+        .and_then(|result| result)
+        .map_err(|e| CrawlifyError::Parse(format!("PDF text of {}: {}", url, e)))?;
+
+    let mut structured_data = serde_json::Map::new();
+    if let Some(author) = &pdf.author {
+        structured_data.insert("pdf:author".to_string(), serde_json::json!([author]));
+    }
+    structured_data.insert("pdf:page_count".to_string(), serde_json::json!([pdf.page_count.to_string()]));
+
+    let mut page_data = empty_page_data();
+    page_data.title = pdf.title;
+    page_data.structured_data = serde_json::Value::Object(structured_data);
+    page_data.main_content = pdf.text;
+    Ok(page_data)
+}
+
+fn parse_text(body: &[u8], url: &Url, is_markdown: bool) -> PageData {
+    let text = String::from_utf8_lossy(body).into_owned();
+    let mut page_data = empty_page_data();
+
+    if is_markdown {
+        page_data.title = text
+            .lines()
+            .find_map(|line| line.strip_prefix("# "))
+            .map(|t| t.trim().to_string());
+        page_data.outlinks = markdown_links(&text, url);
+        page_data.outlinks_with_scores = page_data
+            .outlinks
+            .iter()
+            .map(|url| OutlinkWithScore {
+                url: url.clone(),
+                nlp_score: None,
+            })
+            .collect();
+    }
+
+    page_data.main_content = text;
+    page_data
+}
+
+/// Collects the targets of inline `[text](target)` links, resolved against the page URL.
+fn markdown_links(text: &str, base_url: &Url) -> Vec<String> {
+    let mut links = Vec::new();
+    let mut rest = text;
+    while let Some(pos) = rest.find("](") {
+        rest = &rest[pos + 2..];
+        let Some(end) = rest.find(')') else { break };
+        let target = rest[..end].split_whitespace().next().unwrap_or_default();
+        if let Ok(abs_url) = base_url.join(target) {
+            let abs_url = abs_url.to_string();
+            if !links.contains(&abs_url) {
+                links.push(abs_url);
+            }
+        }
+        rest = &rest[end..];
+    }
+    links
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mime_patterns_match_exact_types_and_wildcards() {
+        assert!(mime_matches("text/html", "text/html"));
+        assert!(mime_matches(" Text/HTML ", "text/html"));
+        assert!(!mime_matches("text/html", "text/plain"));
+        assert!(mime_matches("text/*", "text/markdown"));
+        assert!(!mime_matches("text/*", "application/pdf"));
+        assert!(!mime_matches("text/*", "textual/plain"));
+        assert!(mime_matches("*", "application/pdf"));
+        assert!(mime_matches("*/*", "image/png"));
+    }
+
+    #[test]
+    fn deny_rules_win_over_allow_rules() {
+        let rules = ContentConfig {
+            allow: vec!["text/*".to_string()],
+            deny: vec!["text/csv".to_string()],
+        };
+        assert!(rules.is_allowed("text/html"));
+        assert!(!rules.is_allowed("text/csv"));
+        assert!(!rules.is_allowed("application/pdf"));
+        assert!(ContentConfig::default().is_allowed("application/pdf"));
+    }
+
+    #[test]
+    fn only_text_types_are_extracted() {
+        assert!(extracts_text(Some("text/html"), b"<p>hi</p>"));
+        assert!(extracts_text(Some("text/markdown"), b"# hi"));
+        assert!(extracts_text(None, b"%PDF-1.4"));
+        assert!(!extracts_text(Some("application/json"), b"{}"));
+        assert!(!extracts_text(Some("text/html"), b"\x89PNG\r\n\x1a\n"));
+        assert!(!extracts_text(None, b"PK\x03\x04"));
+    }
+}
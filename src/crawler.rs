@@ -1,63 +1,225 @@
-use crate::config::Config as CrawlerConfig;
-use crate::deduplication::Deduplicator;
-use crate::exporter::{JsonlExporter, Exporter, ExportPage};
+use crate::budget::{BudgetLimit, BudgetTracker};
+use crate::config::{Config as CrawlerConfig, ContentConfig};
+use crate::error::CrawlifyError;
+use crate::error_log::ErrorLog;
+use crate::event_log::EventLog;
+use crate::exporter::{ExportTargets, JsonlExporter};
 use crate::pdf_exporter::PdfExporter;
 use crate::frontier::Frontier;
 use crate::http::HttpClient;
 use crate::monitoring::{Metrics, Monitor};
-use crate::nlp::NlpProcessor;
-use crate::parser;
+use crate::pipeline::{PageOutcome, Pipeline};
+use crate::retry_queue::RetryQueue;
+use crate::scope_config::ScopeConfig;
 use crate::storage::connection::DB;
-use crate::storage::models::EventLevel;
+use crate::storage::models::{CrawlRun, EventLevel, RunCounts, Scope};
 use anyhow::Result;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::Semaphore;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
+
+/// Queues a failed fetch for a later crawl, under the scope the URL belongs to.
+async fn record_failure(retry_queue: Option<&RetryQueue>, scopes: &[Scope], url: &url::Url, error: &CrawlifyError) {
+    let Some(retry_queue) = retry_queue else {
+        return;
+    };
+    let Some(scope) = scopes.iter().find(|s| s.matches(url.as_str())).or(scopes.first()) else {
+        return;
+    };
+    if let Err(e) = retry_queue.failed(scope.id, url, error).await {
+        tracing::error!("Failed to queue {} for retry: {}", url, e);
+    }
+}
+
+/// How the crawl of a scope ended.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ScopeOutcome {
+    Completed,
+    /// A budget limit was reached; see the trackers for which.
+    BudgetExhausted,
+    Interrupted,
+}
+
+/// Waits for the crawl tasks in `handles`. Once `shutdown` is cancelled, tasks still
+/// running after `grace` are aborted and their URLs returned so they can be checkpointed.
+async fn join_tasks(
+    handles: Vec<(url::Url, JoinHandle<()>)>,
+    shutdown: &CancellationToken,
+    grace: Duration,
+) -> Result<Vec<url::Url>> {
+    let mut deadline = None;
+    let mut unfinished = Vec::new();
+    for (url, mut handle) in handles {
+        let until = match deadline {
+            Some(until) => until,
+            None => tokio::select! {
+                result = &mut handle => {
+                    result?;
+                    continue;
+                }
+                _ = shutdown.cancelled() => *deadline.insert(Instant::now() + grace),
+            },
+        };
+        match tokio::time::timeout_at(until, &mut handle).await {
+            Ok(result) => result?,
+            Err(_) => {
+                handle.abort();
+                unfinished.push(url);
+            }
+        }
+    }
+    Ok(unfinished)
+}
+
+async fn record_success(retry_queue: Option<&RetryQueue>, url: &url::Url) {
+    if let Some(retry_queue) = retry_queue {
+        if let Err(e) = retry_queue.succeeded(url).await {
+            tracing::error!("Failed to clear retry entry for {}: {}", url, e);
+        }
+    }
+}
+
+/// What a crawl or revisit of one scope works with.
+struct ScopeSetup {
+    scope: Scope,
+    http_client: Arc<HttpClient>,
+    pipeline: Pipeline,
+    exporter: JsonlExporter,
+    content_rules: Arc<ContentConfig>,
+    concurrency: usize,
+}
 
 pub struct Crawler {
     config: CrawlerConfig,
     db: DB,
     http_client: HttpClient,
     frontier: Frontier,
-    exporter: JsonlExporter,
-    pdf_exporter: PdfExporter,
+    exports: Arc<ExportTargets>,
     metrics: Arc<Metrics>,
+    run: CrawlRun,
+    events: EventLog,
+    shutdown: CancellationToken,
+    interrupted: bool,
+    budget: Arc<BudgetTracker>,
 }
 
 impl Crawler {
+    /// A crawler for all active scopes.
     pub async fn new(config: CrawlerConfig) -> Result<Self> {
+        Self::start(config, None).await
+    }
+
+    /// A crawler whose run records `scopes` as the scopes it crawls.
+    pub async fn for_scopes(config: CrawlerConfig, scopes: Vec<String>) -> Result<Self> {
+        Self::start(config, Some(scopes)).await
+    }
+
+    async fn start(config: CrawlerConfig, scopes: Option<Vec<String>>) -> Result<Self> {
         let db = DB::new(&config.db_path).await?;
         db.migrate().await?;
-        let http_client = HttpClient::new(&config)?;
-        let frontier = if let Ok(Some(f)) = Frontier::load_state(&db).await {
-            tracing::info!("Loaded frontier state from database");
-            f
-        } else {
-            Frontier::new()
+        let scopes = match scopes {
+            Some(scopes) => scopes,
+            None => db.get_active_scopes().await?.into_iter().map(|s| s.pattern).collect(),
         };
-        let exporter = JsonlExporter::new(&config.export_path).await?;
-        let pdf_exporter = PdfExporter::new("crawled_pdfs")?;
         let metrics = Arc::new(Metrics::new());
+        let events = EventLog::new(db.clone());
+        let http_client = HttpClient::new(&config)?
+            .with_metrics(metrics.clone())
+            .with_events(events.clone());
+        let frontier = Frontier::new();
+        let run = CrawlRun::start(&*db.pool.get().await?, &scopes)?;
+        events
+            .log(EventLevel::Info, "Crawl started", Some(serde_json::json!({ "run_id": run.id })))
+            .await;
+
+        let budget = Arc::new(BudgetTracker::new(config.budget.clone()));
 
         Ok(Crawler {
             config,
             db,
             http_client,
             frontier,
-            exporter,
-            pdf_exporter,
+            exports: Arc::new(ExportTargets::new()),
             metrics,
+            run,
+            events,
+            shutdown: CancellationToken::new(),
+            interrupted: false,
+            budget,
         })
     }
 
-    #[allow(dead_code)]
-    fn log_event(&self, level: EventLevel, message: &str, context: Option<&str>) {
-        // For now, just log to tracing - Event::create needs proper connection handling
-        match level {
-            EventLevel::Info => tracing::info!("{}: {}", message, context.unwrap_or("")),
-            EventLevel::Warn => tracing::warn!("{}: {}", message, context.unwrap_or("")),
-            EventLevel::Error => tracing::error!("{}: {}", message, context.unwrap_or("")),
-            EventLevel::Debug => tracing::debug!("{}: {}", message, context.unwrap_or("")),
+    /// Stops the crawl gracefully once `shutdown` is cancelled.
+    pub fn with_shutdown(mut self, shutdown: CancellationToken) -> Self {
+        self.shutdown = shutdown;
+        self
+    }
+
+    /// Exports through `exports`, shared with other crawlers.
+    pub fn with_exports(mut self, exports: Arc<ExportTargets>) -> Self {
+        self.exports = exports;
+        self
+    }
+
+    /// The run budget limit that stopped the crawl, if any.
+    pub fn budget_limit(&self) -> Option<BudgetLimit> {
+        self.budget.exhausted_limit()
+    }
+
+    /// True once a shutdown cut a scope's crawl short.
+    pub fn is_interrupted(&self) -> bool {
+        self.interrupted
+    }
+
+    async fn log_event(&self, level: EventLevel, message: &str, context: serde_json::Value) {
+        self.events.log(level, message, Some(context)).await;
+    }
+
+    /// Records the end of the crawl run and its page counts, with the error that
+    /// stopped it if any.
+    pub async fn finish(&self, error: Option<&anyhow::Error>) -> Result<()> {
+        let counts = RunCounts {
+            fetched: self.metrics.pages_fetched.load(Ordering::Relaxed),
+            exported: self.metrics.pages_exported.load(Ordering::Relaxed),
+            duplicate: self.metrics.pages_duplicate.load(Ordering::Relaxed),
+            failed: self.metrics.pages_failed(),
+        };
+        let budget_limit = self.budget_limit();
+        let exit_reason = match (error, self.interrupted, budget_limit) {
+            (Some(_), _, _) => "failed",
+            (None, true, _) => "interrupted",
+            (None, false, Some(_)) => "budget",
+            (None, false, None) => "completed",
+        };
+        let budget_limit = budget_limit.map(|limit| limit.as_str());
+        self.run.finish(&*self.db.pool.get().await?, exit_reason, budget_limit, counts)?;
+        match error {
+            Some(e) => {
+                let context = serde_json::json!({ "run_id": self.run.id, "error": e.to_string() });
+                self.log_event(EventLevel::Error, "Crawl failed", context).await;
+            }
+            None => {
+                let message = match exit_reason {
+                    "interrupted" => "Crawl interrupted",
+                    "budget" => "Crawl stopped by budget",
+                    _ => "Crawl finished",
+                };
+                let context = serde_json::json!({
+                    "run_id": self.run.id,
+                    "budget_limit": budget_limit,
+                    "fetched": counts.fetched,
+                    "exported": counts.exported,
+                    "duplicate": counts.duplicate,
+                    "failed": counts.failed,
+                });
+                self.log_event(EventLevel::Info, message, context).await;
+            }
         }
+        Ok(())
     }
 
     #[allow(dead_code)]
@@ -71,54 +233,217 @@ impl Crawler {
         let monitor = Monitor::new(self.metrics.clone());
         let db = self.db.clone();
         let metrics = self.metrics.clone();
-        let http_client = Arc::new(self.http_client.clone());
-        let exporter = Arc::new(self.exporter.clone());
-        let pdf_exporter = Arc::new(self.pdf_exporter.clone());
-        let nlp_processor = Arc::new(NlpProcessor::new(&self.config.nlp)?);
-        let deduplicator = Arc::new(Deduplicator::new(self.db.clone()));
-        let frontier = self.frontier.clone();
-        let (global_concurrency, _) = self.config.get_concurrency();
+        let ScopeSetup { scope, http_client, pipeline, exporter, content_rules, concurrency } =
+            self.prepare_scope(root_url).await?;
+        let frontier = match Frontier::load_state(&self.db, scope.id).await? {
+            Some(frontier) => {
+                tracing::info!("Resuming {} from its checkpoint ({} URLs queued)", root_url, frontier.size());
+                frontier
+            }
+            None => self.frontier.clone(),
+        };
+        // Cassette misses during replay are not worth retrying later.
+        let retry_queue = (!http_client.is_replaying()).then(|| RetryQueue::new(&self.config.retry, self.db.clone()));
+
+        let scope_context = serde_json::json!({ "run_id": self.run.id, "scope": root_url });
+        self.log_event(EventLevel::Info, "Scope crawl started", scope_context.clone()).await;
 
         // Start monitoring in the background
-        tokio::spawn(async move {
+        let monitor = tokio::spawn(async move {
             monitor.run().await;
         });
 
+        let scope_budget = Arc::new(BudgetTracker::child(self.budget.clone(), scope.budget.clone()));
+
         // Call helper function to avoid lifetime issues
-        Self::run_crawler_loop(
+        let outcome = Self::run_crawler_loop(
             root_url,
+            scope.id,
             db,
             metrics,
+            http_client.clone(),
+            pipeline,
+            frontier,
+            retry_queue,
+            content_rules,
+            concurrency,
+            self.shutdown.clone(),
+            self.config.shutdown_grace,
+            scope_budget.clone(),
+        ).await;
+        monitor.abort();
+        let outcome = outcome?;
+
+        let dead_hosts = http_client.dead_hosts();
+        if !dead_hosts.is_empty() {
+            tracing::warn!("Hosts marked dead during this crawl: {}", dead_hosts.join(", "));
+        }
+        match outcome {
+            ScopeOutcome::Completed => {
+                Scope::mark_crawled(&*self.db.pool.get().await?, root_url)?;
+                self.log_event(EventLevel::Info, "Scope crawl finished", scope_context).await;
+            }
+            ScopeOutcome::BudgetExhausted => {
+                Scope::mark_crawled(&*self.db.pool.get().await?, root_url)?;
+                let (owner, limit) = match (self.budget.exhausted_limit(), scope_budget.exhausted_limit()) {
+                    (Some(limit), _) => ("run", Some(limit)),
+                    (None, limit) => ("scope", limit),
+                };
+                let limit = limit.map(|limit| limit.as_str());
+                tracing::warn!("Stopped crawling {}: {} budget {} reached", root_url, owner, limit.unwrap_or("limit"));
+                let context = serde_json::json!({ "run_id": self.run.id, "scope": root_url, "budget": owner, "limit": limit });
+                self.log_event(EventLevel::Warn, "Scope crawl stopped by budget", context).await;
+            }
+            ScopeOutcome::Interrupted => {
+                self.interrupted = true;
+                self.log_event(EventLevel::Warn, "Scope crawl interrupted", scope_context).await;
+            }
+        }
+        exporter.flush().await?;
+
+        http_client.save_cookies()
+    }
+
+    /// Sets up the client, pipeline and limits of the active scope `root_url` from its
+    /// effective settings.
+    async fn prepare_scope(&self, root_url: &str) -> Result<ScopeSetup> {
+        let Some(scope) = self.db.get_active_scopes().await?.into_iter().find(|s| s.pattern == root_url) else {
+            anyhow::bail!("No active scope with pattern {}", root_url);
+        };
+        let settings = Scope::settings(&*self.db.pool.get().await?, scope.id)?;
+        let scope_config = ScopeConfig::resolve(&self.config, &scope, &settings)
+            .map_err(|e| anyhow::anyhow!("Invalid settings for scope {}: {}", root_url, e))?;
+        let http_client = Arc::new(
+            if self.config.auth_for(root_url).is_some() || self.config.login_for(root_url).is_some() {
+                HttpClient::for_scope(&self.config, root_url)?
+                    .with_metrics(self.metrics.clone())
+                    .with_events(self.events.clone())
+            } else {
+                self.http_client.clone()
+            }
+            .with_request_delay(scope_config.request_delay.value),
+        );
+        http_client.login().await?;
+        let exporter = self.exports.get(&scope_config.export_path.value).await?;
+        let mut pipeline_config = self.config.clone();
+        pipeline_config.nlp = scope_config.nlp();
+        let pipeline = Pipeline::new(
+            &pipeline_config,
+            self.db.clone(),
+            exporter.clone(),
+            Some(PdfExporter::new(&scope_config.pdf_dir.value)?),
+        )?
+        .with_error_log(ErrorLog::new(self.db.clone(), Some(self.run.id), self.db.get_active_scopes().await?))
+        .with_run(self.run.id)
+        .with_freshness(self.config.revisit.clone());
+        Ok(ScopeSetup {
+            content_rules: Arc::new(self.config.content_rules_for(root_url).clone()),
+            concurrency: scope_config.concurrency.value,
+            scope,
             http_client,
+            pipeline,
             exporter,
-            pdf_exporter,
-            nlp_processor,
-            deduplicator,
-            frontier,
-            global_concurrency,
-        ).await
+        })
+    }
+
+    /// Fetches `urls` of the scope `root_url` again without following their links.
+    /// Unchanged pages answer conditional requests with 304, and every fetch updates the
+    /// change statistics that schedule the next revisit.
+    pub async fn revisit(&mut self, root_url: &str, urls: Vec<url::Url>) -> Result<()> {
+        let ScopeSetup { scope, http_client, pipeline, exporter, content_rules, concurrency } =
+            self.prepare_scope(root_url).await?;
+        let scopes = Arc::new(self.db.get_active_scopes().await?);
+        let budget = Arc::new(BudgetTracker::child(self.budget.clone(), scope.budget.clone()));
+        let semaphore = Arc::new(Semaphore::new(concurrency));
+        let context = serde_json::json!({ "run_id": self.run.id, "scope": root_url, "pages": urls.len() });
+        self.log_event(EventLevel::Info, "Scope revisit started", context).await;
+
+        let mut handles = Vec::new();
+        for url in urls {
+            let permit = tokio::select! {
+                permit = semaphore.clone().acquire_owned() => permit?,
+                _ = self.shutdown.cancelled() => break,
+            };
+            if budget.is_exhausted() {
+                break;
+            }
+            budget.add_page();
+            let http_client = http_client.clone();
+            let pipeline = pipeline.clone();
+            let db = self.db.clone();
+            let metrics = self.metrics.clone();
+            let content_rules = content_rules.clone();
+            let scopes = scopes.clone();
+            let budget = budget.clone();
+
+            let task_url = url.clone();
+            handles.push((task_url, tokio::spawn(async move {
+                metrics.inc_requests();
+                let in_scope = |u: &url::Url| scopes.iter().any(|s| s.matches(u.as_str()));
+                let response = http_client.get_with_retry(&db, &url, &in_scope).await;
+                let bytes = response.as_ref().map(|r| r.body.len() as u64).unwrap_or(0);
+                metrics.add_bytes_in(bytes);
+                budget.add_bytes(bytes);
+
+                // Pages that fail stay due and are tried again by the next revisit.
+                match response {
+                    Ok(response) => {
+                        metrics.inc_pages_fetched(url.as_str());
+                        match pipeline.process(&url, &response, &content_rules).await {
+                            Ok(PageOutcome::Exported(_)) => {
+                                metrics.inc_pages_exported();
+                                budget.add_exported();
+                            }
+                            Ok(PageOutcome::Duplicate(_)) => metrics.inc_pages_duplicate(),
+                            Ok(PageOutcome::Skipped | PageOutcome::NotModified | PageOutcome::NoMatch | PageOutcome::Recorded(_)) => {}
+                            Err(e) => {
+                                tracing::error!("Failed to process {}: {}", url, e);
+                                metrics.mark_failed(url.as_str());
+                            }
+                        }
+                    }
+                    Err(CrawlifyError::CircuitOpen { .. }) => {}
+                    Err(e) => {
+                        tracing::warn!("Failed to revisit {}: {}", url, e);
+                        metrics.mark_failed(url.as_str());
+                        pipeline.record_error(&url, &e, None).await;
+                    }
+                }
+                drop(permit);
+            })));
+        }
+        join_tasks(handles, &self.shutdown, self.config.shutdown_grace).await?;
+        if self.shutdown.is_cancelled() {
+            self.interrupted = true;
+        }
+        exporter.flush().await?;
+
+        http_client.save_cookies()
     }
 
+    /// Crawls one scope until its frontier is empty, `budget` is spent or `shutdown`
+    /// interrupts it. An interrupted crawl checkpoints the URLs still to fetch.
+    #[allow(clippy::too_many_arguments)]
     async fn run_crawler_loop(
         root_url: &str,
+        scope_id: i64,
         db: crate::storage::connection::DB,
         metrics: Arc<Metrics>,
         http_client: Arc<HttpClient>,
-        exporter: Arc<JsonlExporter>,
-        pdf_exporter: Arc<PdfExporter>,
-        nlp_processor: Arc<NlpProcessor>,
-        deduplicator: Arc<Deduplicator>,
+        pipeline: Pipeline,
         frontier: Frontier,
+        retry_queue: Option<RetryQueue>,
+        content_rules: Arc<ContentConfig>,
         global_concurrency: usize,
-    ) -> Result<()> {
+        shutdown: CancellationToken,
+        grace: Duration,
+        budget: Arc<BudgetTracker>,
+    ) -> Result<ScopeOutcome> {
         // Convert pattern to base URL by removing wildcards
-        let base_url = if root_url.ends_with("/*") {
-            &root_url[..root_url.len()-2]
-        } else if root_url.ends_with("*") {
-            &root_url[..root_url.len()-1]
-        } else {
-            root_url
-        };
+        let base_url = root_url
+            .strip_suffix("/*")
+            .or_else(|| root_url.strip_suffix('*'))
+            .unwrap_or(root_url);
         let root_url = url::Url::parse(base_url)?;
         let scopes = Arc::new(db.get_active_scopes().await?);
         let frontier = Arc::new(tokio::sync::Mutex::new(frontier));
@@ -140,7 +465,17 @@ impl Crawler {
         if frontier_guard.is_empty() {
             // Get first scope as default - this should be improved
             if let Some(scope) = scopes.first() {
-                frontier_guard.add_url(root_url.clone(), scope, false)?;
+                frontier_guard.add_url(root_url.clone(), scope, false, 0)?;
+            }
+        }
+        if let Some(retry_queue) = &retry_queue {
+            let scope_ids: Vec<i64> = scopes.iter().map(|s| s.id).collect();
+            let due = retry_queue.due(&scope_ids).await?;
+            if !due.is_empty() {
+                tracing::info!("Retrying {} previously failed URLs", due.len());
+            }
+            for url in due {
+                frontier_guard.requeue(url);
             }
         }
         drop(frontier_guard);
@@ -148,65 +483,119 @@ impl Crawler {
         // Phase 1: Discovery - Add and scan all outlinks first, collect URLs for phase 2
         tracing::info!("Phase 1: Starting outlink discovery phase");
         let discovered_urls = Arc::new(tokio::sync::Mutex::new(Vec::new()));
+        let discovered_outlinks = Arc::new(tokio::sync::Mutex::new(Vec::new()));
+        // Failures are recorded once per crawl, so phase 2 does not fetch these again.
+        let failed = Arc::new(tokio::sync::Mutex::new(std::collections::HashSet::new()));
         let discovery_semaphore = Arc::new(Semaphore::new(global_concurrency));
         let mut discovery_handles = vec![];
         let mut discovery_empty_checks = 0;
+        // Discovery stops once it has visited as many pages as the budget lets phase 2 fetch.
+        let mut visited = 0;
+        let mut capped = false;
 
         loop {
-            let permit = discovery_semaphore.clone().acquire_owned().await?;
+            let permit = tokio::select! {
+                permit = discovery_semaphore.clone().acquire_owned() => permit?,
+                _ = shutdown.cancelled() => break,
+            };
+            if budget.is_exhausted() {
+                break;
+            }
+            if budget.remaining_pages().is_some_and(|remaining| visited >= remaining) {
+                capped = true;
+                break;
+            }
             let mut frontier_guard = frontier.lock().await;
 
             if let Some(url) = frontier_guard.get_next_url() {
                 discovery_empty_checks = 0;
+                visited += 1;
+                let depth = frontier_guard.depth(&url);
                 drop(frontier_guard);
                 
                 let http_client = http_client.clone();
-                let nlp_processor = nlp_processor.clone();
+                let pipeline = pipeline.clone();
                 let db = db.clone();
                 let metrics = metrics.clone();
                 let scopes_clone = scopes.clone();
                 let frontier_clone = frontier.clone();
                 let discovered_urls_clone = discovered_urls.clone();
-
-                discovery_handles.push(tokio::spawn(async move {
+                let discovered_outlinks_clone = discovered_outlinks.clone();
+                let failed = failed.clone();
+                let content_rules = content_rules.clone();
+                let retry_queue = retry_queue.clone();
+                let budget = budget.clone();
+
+                let task_url = url.clone();
+                discovery_handles.push((task_url, tokio::spawn(async move {
                     metrics.inc_requests();
-                    let response = http_client.get_with_retry(&db, &url).await;
-                    metrics.add_bytes_in(response.as_ref().map(|r| r.content_length().unwrap_or(0)).unwrap_or(0));
-
-                    let page_data = match response {
-                        Ok(response) => {
-                            if !response.status().is_success() {
-                                return;
+                    let in_scope = |u: &url::Url| scopes_clone.iter().any(|s| s.matches(u.as_str()));
+                    let response = http_client.get_with_retry(&db, &url, &in_scope).await;
+                    let bytes = response.as_ref().map(|r| r.body.len() as u64).unwrap_or(0);
+                    metrics.add_bytes_in(bytes);
+                    budget.add_bytes(bytes);
+
+                    let response = match response {
+                        Ok(response) => response,
+                        Err(CrawlifyError::CircuitOpen { host, until }) => {
+                            let mut frontier_guard = frontier_clone.lock().await;
+                            frontier_guard.pause_host(&host, until);
+                            frontier_guard.requeue(url);
+                            return;
+                        }
+                        Err(CrawlifyError::HostDead(host)) => {
+                            let dropped = frontier_clone.lock().await.drop_host(&host);
+                            tracing::warn!("Dropped {} queued URLs of dead host {}", dropped.len() + 1, host);
+                            let err = CrawlifyError::HostDead(host);
+                            pipeline.record_error(&url, &err, None).await;
+                            for url in std::iter::once(url).chain(dropped) {
+                                metrics.mark_failed(url.as_str());
+                                record_failure(retry_queue.as_ref(), &scopes_clone, &url, &err).await;
+                                failed.lock().await.insert(url);
                             }
-                            let text = response.text().await.unwrap_or_default();
-                            parser::parse(text.as_bytes(), &url)
+                            return;
                         }
-                        Err(_e) => {
+                        Err(e) => {
+                            tracing::warn!("Failed to fetch {}: {}", url, e);
+                            metrics.mark_failed(url.as_str());
+                            pipeline.record_error(&url, &e, None).await;
+                            record_failure(retry_queue.as_ref(), &scopes_clone, &url, &e).await;
+                            failed.lock().await.insert(url);
                             return;
                         }
                     };
+                    record_success(retry_queue.as_ref(), &url).await;
 
                     // Discovery phase: Always process and add ALL outlinks
-                    let mut processed_page_data = page_data.clone();
-                    nlp_processor.score_outlinks(&mut processed_page_data.outlinks_with_scores);
+                    let processed_page_data = match pipeline.extract(&response.url, &response, &content_rules).await {
+                        Ok(Some(page_data)) => page_data,
+                        Ok(None) => return,
+                        Err(e) => {
+                            tracing::warn!("{}", e);
+                            pipeline.record_error(&response.url, &e, Some(response.status.as_u16())).await;
+                            failed.lock().await.insert(url);
+                            return;
+                        }
+                    };
+                    let final_url = response.url;
                     
                     let mut new_urls = Vec::new();
                     for outlink in &processed_page_data.outlinks {
                         if let Ok(outlink_url) = url::Url::parse(outlink) {
-                            if let (Some(current_host), Some(outlink_host)) = (url.host_str(), outlink_url.host_str()) {
+                            if let (Some(current_host), Some(outlink_host)) = (final_url.host_str(), outlink_url.host_str()) {
                                 if current_host == outlink_host {
                                     new_urls.push(outlink_url.clone());
                                 }
                             }
                         }
                     }
+                    if !budget.allows_depth(depth + 1) {
+                        new_urls.clear();
+                    }
                     
                     // Add to discovered URLs for phase 2
-                    {
-                        let mut discovered_guard = discovered_urls_clone.lock().await;
-                        discovered_guard.push(url.clone());
-                        discovered_guard.extend(new_urls.iter().cloned());
-                    }
+                    discovered_urls_clone.lock().await.push(url.clone());
+                    discovered_outlinks_clone.lock().await.extend(new_urls.iter().cloned());
                     
                     if !new_urls.is_empty() {
                         if let Some(scope) = scopes_clone.first() {
@@ -215,7 +604,7 @@ impl Crawler {
                             tokio::spawn(async move {
                                 let mut frontier_guard = frontier_for_urls.lock().await;
                                 for new_url in new_urls {
-                                    if let Err(e) = frontier_guard.add_url(new_url.clone(), &scope_for_urls, false) {
+                                    if let Err(e) = frontier_guard.add_url(new_url.clone(), &scope_for_urls, false, depth + 1) {
                                         tracing::warn!("Failed to add outlink {} to frontier: {}", new_url, e);
                                     } else {
                                         tracing::info!("Added outlink to frontier: {}", new_url);
@@ -225,18 +614,28 @@ impl Crawler {
                         }
                     }
                     drop(permit);
-                }));
+                })));
             } else {
+                // URLs of paused hosts are still queued; wait for their cool-down.
+                let paused = !frontier_guard.is_empty();
                 drop(frontier_guard);
                 drop(permit);
                 
-                if !discovery_handles.is_empty() {
-                    while let Some(handle) = discovery_handles.pop() {
-                        handle.await?;
-                        break;
+                if let Some((url, mut handle)) = discovery_handles.pop() {
+                    tokio::select! {
+                        result = &mut handle => result?,
+                        _ = shutdown.cancelled() => {
+                            discovery_handles.push((url, handle));
+                            break;
+                        }
                     }
                     continue;
                 }
+
+                if paused {
+                    tokio::time::sleep(tokio::time::Duration::from_millis(250)).await;
+                    continue;
+                }
                 
                 discovery_empty_checks += 1;
                 if discovery_empty_checks < 3 {
@@ -249,142 +648,175 @@ impl Crawler {
         }
 
         // Wait for all discovery tasks to complete
-        for handle in discovery_handles {
-            handle.await?;
+        let unfinished = join_tasks(discovery_handles, &shutdown, grace).await?;
+
+        // Aborted tasks may not have released their handle on the lists yet.
+        let discovered_urls = std::mem::take(&mut *discovered_urls.lock().await);
+        let failed = std::mem::take(&mut *failed.lock().await);
+        let discovered_outlinks: Vec<url::Url> = std::mem::take(&mut *discovered_outlinks.lock().await)
+            .into_iter()
+            .filter(|url| !failed.contains(url))
+            .collect();
+        if shutdown.is_cancelled() {
+            // Discovered pages are fetched again on resume so their outlinks are not lost.
+            let unfinished = unfinished.into_iter().chain(discovered_urls).chain(discovered_outlinks);
+            Self::checkpoint(&db, scope_id, &frontier, unfinished).await?;
+            return Ok(ScopeOutcome::Interrupted);
         }
-
-        let discovered_urls = Arc::try_unwrap(discovered_urls).unwrap().into_inner();
-        let unique_urls: std::collections::HashSet<_> = discovered_urls.into_iter().collect();
+        // Outlinks discovery never visited would take phase 2 past the page budget.
+        capped = capped && !frontier.lock().await.is_empty();
+        // Discovery already charged these pages' bytes to the budget.
+        let charged: Arc<std::collections::HashSet<url::Url>> = Arc::new(discovered_urls.iter().cloned().collect());
+        let unique_urls: std::collections::HashSet<_> = if capped {
+            discovered_urls.into_iter().collect()
+        } else {
+            discovered_urls.into_iter().chain(discovered_outlinks).collect()
+        };
         tracing::info!("Phase 1 complete: {} unique URLs discovered and ready for export", unique_urls.len());
 
         // Phase 2: Export - Process discovered URLs with NLP filtering
         tracing::info!("Phase 2: Starting export phase with NLP filtering");
         let export_semaphore = Arc::new(Semaphore::new(global_concurrency));
-        let mut export_handles = vec![];
-
-        for url in unique_urls {
-            let permit = export_semaphore.clone().acquire_owned().await?;
-            let http_client = http_client.clone();
-            let exporter = exporter.clone();
-            let pdf_exporter = pdf_exporter.clone();
-            let nlp_processor = nlp_processor.clone();
-            let deduplicator = deduplicator.clone();
-            let db = db.clone();
-            let metrics = metrics.clone();
-
-            export_handles.push(tokio::spawn(async move {
-                metrics.inc_requests();
-                let response = http_client.get_with_retry(&db, &url).await;
-                metrics.add_bytes_in(response.as_ref().map(|r| r.content_length().unwrap_or(0)).unwrap_or(0));
-
-                let page_data = match response {
-                    Ok(response) => {
-                        if !response.status().is_success() {
-                            drop(permit);
-                            return;
-                        }
-                        let text = response.text().await.unwrap_or_default();
-                        parser::parse(text.as_bytes(), &url)
-                    }
-                    Err(_e) => {
-                        drop(permit);
-                        return;
+        let mut pending: Vec<url::Url> = unique_urls.into_iter().collect();
+
+        // URLs whose host is cooling down are retried in a later round.
+        while !pending.is_empty() {
+            let deferred = Arc::new(tokio::sync::Mutex::new(Vec::new()));
+            let mut export_handles: Vec<(url::Url, JoinHandle<()>)> = vec![];
+
+            let mut urls = std::mem::take(&mut pending).into_iter();
+            let mut unstarted = Vec::new();
+            for url in urls.by_ref() {
+                let permit = tokio::select! {
+                    permit = export_semaphore.clone().acquire_owned() => permit?,
+                    _ = shutdown.cancelled() => {
+                        unstarted.push(url);
+                        break;
                     }
                 };
-
-                let mut processed_page_data = page_data.clone();
-                nlp_processor.score_outlinks(&mut processed_page_data.outlinks_with_scores);
-
-                // NLP filtering for export phase
-                if !nlp_processor.is_match(&processed_page_data.main_content) {
-                    drop(permit);
-                    return;
+                // Every page in flight may still be exported; wait rather than overshoot max_exported.
+                loop {
+                    let running = export_handles.iter().filter(|(_, handle)| !handle.is_finished()).count() as u64;
+                    if !budget.remaining_exported().is_some_and(|remaining| running > 0 && running >= remaining) {
+                        break;
+                    }
+                    tokio::select! {
+                        _ = tokio::time::sleep(tokio::time::Duration::from_millis(50)) => {}
+                        _ = shutdown.cancelled() => break,
+                    }
                 }
-
-                let text_hash = crate::deduplication::text_hash(&processed_page_data.main_content);
-                let sim_hash = crate::deduplication::sim_hash(&processed_page_data.main_content);
-                let page = match crate::storage::models::Page::create(
-                    &db,
-                    url.as_str(),
-                    Some(text_hash),
-                    Some(sim_hash),
-                    processed_page_data.title.clone(),
-                    processed_page_data.canonical_url.clone(),
-                ) {
-                    Ok(p) => p,
-                    Err(_e) => {
-                        drop(permit);
-                        return;
+                if shutdown.is_cancelled() {
+                    unstarted.push(url);
+                    break;
+                }
+                if budget.is_exhausted() {
+                    break;
+                }
+                budget.add_page();
+                let http_client = http_client.clone();
+                let pipeline = pipeline.clone();
+                let db = db.clone();
+                let metrics = metrics.clone();
+                let content_rules = content_rules.clone();
+                let scopes = scopes.clone();
+                let deferred = deferred.clone();
+                let retry_queue = retry_queue.clone();
+                let budget = budget.clone();
+                let charged = charged.clone();
+
+                let task_url = url.clone();
+                export_handles.push((task_url, tokio::spawn(async move {
+                    metrics.inc_requests();
+                    let in_scope = |u: &url::Url| scopes.iter().any(|s| s.matches(u.as_str()));
+                    let response = http_client.get_with_retry(&db, &url, &in_scope).await;
+                    let bytes = response.as_ref().map(|r| r.body.len() as u64).unwrap_or(0);
+                    metrics.add_bytes_in(bytes);
+                    if !charged.contains(&url) {
+                        budget.add_bytes(bytes);
                     }
-                };
 
-                if deduplicator.is_duplicate(&page).await {
+                    match response {
+                        Ok(response) => {
+                            record_success(retry_queue.as_ref(), &url).await;
+                            metrics.inc_pages_fetched(url.as_str());
+                            match pipeline.process(&url, &response, &content_rules).await {
+                                Ok(PageOutcome::Exported(_)) => {
+                                    metrics.inc_pages_exported();
+                                    budget.add_exported();
+                                }
+                                Ok(PageOutcome::Duplicate(_)) => metrics.inc_pages_duplicate(),
+                                Ok(PageOutcome::Skipped | PageOutcome::NotModified | PageOutcome::NoMatch | PageOutcome::Recorded(_)) => {}
+                                Err(e) => {
+                                    tracing::error!("Failed to process {}: {}", url, e);
+                                    metrics.mark_failed(url.as_str());
+                                }
+                            }
+                        }
+                        Err(CrawlifyError::CircuitOpen { until, .. }) => deferred.lock().await.push((url, until)),
+                        Err(e) => {
+                            metrics.mark_failed(url.as_str());
+                            pipeline.record_error(&url, &e, None).await;
+                            record_failure(retry_queue.as_ref(), &scopes, &url, &e).await;
+                        }
+                    }
                     drop(permit);
-                    return;
-                }
+                })));
+            }
 
-                // Export with NLP filtering on outlinks
-                let filtered_page_data = if nlp_processor.is_enabled() {
-                    let filtered_outlinks: Vec<String> = processed_page_data.outlinks_with_scores
-                        .iter()
-                        .filter(|outlink| outlink.nlp_score == Some(1))
-                        .map(|outlink| outlink.url.clone())
-                        .collect();
-                    
-                    crate::parser::PageData {
-                        title: processed_page_data.title.clone(),
-                        canonical_url: processed_page_data.canonical_url.clone(),
-                        outlinks: filtered_outlinks,
-                        outlinks_with_scores: processed_page_data.outlinks_with_scores.clone(),
-                        structured_data: processed_page_data.structured_data.clone(),
-                        main_content: processed_page_data.main_content.clone(),
-                    }
-                } else {
-                    processed_page_data.clone()
-                };
-                
-                let base_export_page = crate::storage::models::Page {
-                    id: page.id,
-                    url: page.url.clone(),
-                    canonical_url: page.canonical_url.clone(),
-                    title: page.title.clone(),
-                    text_hash: page.text_hash.clone(),
-                    sim_hash: page.sim_hash.clone(),
-                    fetched_at: page.fetched_at,
-                    status_code: page.status_code,
-                    content_length: page.content_length,
-                    meta_json: Some(serde_json::to_string(&filtered_page_data.structured_data).unwrap_or_default()),
-                    etag: page.etag.clone(),
-                    last_modified: page.last_modified.clone(),
-                    created_at: page.created_at,
-                };
+            // Wait for all export tasks to complete
+            let unfinished = join_tasks(export_handles, &shutdown, grace).await?;
 
-                // Create enhanced export page with outlinks_with_scores
-                let mut enhanced_export_page = ExportPage::from(base_export_page.clone());
-                enhanced_export_page.outlinks_with_scores = filtered_page_data.outlinks_with_scores.clone();
-                
-                if let Err(_e) = exporter.export_enhanced(&enhanced_export_page).await {
-                    // Error logging
-                }
-                
-                if let Ok(pdf_filename) = pdf_exporter.export_page_to_pdf(&base_export_page).await {
-                    tracing::info!("Exporting as pdf name: {}", pdf_filename);
-                } else {
-                    tracing::error!("Failed to export PDF for {}", url);
+            let deferred = std::mem::take(&mut *deferred.lock().await);
+            let mut remaining: Vec<url::Url> = deferred.iter().map(|(url, _)| url.clone()).collect();
+            if shutdown.is_cancelled() {
+                remaining.extend(unstarted.into_iter().chain(urls).chain(unfinished));
+                Self::checkpoint(&db, scope_id, &frontier, remaining).await?;
+                return Ok(ScopeOutcome::Interrupted);
+            }
+            if budget.is_exhausted() {
+                break;
+            }
+            if let Some(until) = deferred.iter().map(|(_, until)| *until).min() {
+                tracing::info!("Waiting for {} URLs of paused hosts", deferred.len());
+                tokio::select! {
+                    _ = tokio::time::sleep_until(until.into()) => {}
+                    _ = shutdown.cancelled() => {}
                 }
-                
-                tracing::info!("Exported page: {}", url);
-                drop(permit);
-            }));
+            }
+            pending = remaining;
         }
 
-        // Wait for all export tasks to complete
-        for handle in export_handles {
-            handle.await?;
+        db.clear_frontier_state(scope_id).await?;
+        if capped {
+            budget.exhaust_pages();
+        }
+        if budget.is_exhausted() {
+            tracing::info!("Phase 2 stopped: crawl budget reached");
+            return Ok(ScopeOutcome::BudgetExhausted);
         }
-
         tracing::info!("Phase 2 complete: All matching pages exported");
 
+        Ok(ScopeOutcome::Completed)
+    }
+
+    /// Saves the frontier with `unfinished` queued again, so the next crawl of the
+    /// scope picks up where this one stopped.
+    async fn checkpoint(
+        db: &DB,
+        scope_id: i64,
+        frontier: &tokio::sync::Mutex<Frontier>,
+        unfinished: impl IntoIterator<Item = url::Url>,
+    ) -> Result<()> {
+        let mut frontier = frontier.lock().await;
+        for url in unfinished {
+            frontier.requeue(url);
+        }
+        if frontier.is_empty() {
+            db.clear_frontier_state(scope_id).await?;
+        } else {
+            frontier.save_state(db, scope_id).await?;
+            tracing::info!("Checkpointed {} queued URLs for the next crawl", frontier.size());
+        }
         Ok(())
     }
 }
\ No newline at end of file
@@ -0,0 +1,173 @@
+//! `crawlify daemon`: recrawls scopes on their schedules. Each due scope gets a crawl run
+//! of its own; a scope is never crawled twice at once, and a failed run is recorded and
+//! tried again at the scope's next due time. With `revisit.enabled` it also runs a
+//! revisit pass over the pages due for one every `revisit.interval`.
+
+use crate::config::Config;
+use crate::crawler::Crawler;
+use crate::error::CrawlifyError;
+use crate::exporter::ExportTargets;
+use crate::freshness;
+use crate::scope_config::ScopeConfig;
+use crate::shutdown;
+use crate::storage::models::{Page, PageFreshness, Scope};
+use crate::storage::Storage;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
+
+pub async fn run(config: Config, poll: Duration) -> Result<(), CrawlifyError> {
+    let storage = Storage::new(&config.db_path).await?;
+    storage.init().await?;
+    let shutdown = shutdown::listen();
+    // Runs append to the export files so earlier runs' pages are kept.
+    let exports = Arc::new(ExportTargets::appending());
+    let mut running: HashMap<i64, JoinHandle<()>> = HashMap::new();
+    // When each scope's latest run started, so a failed run is not retried on every poll.
+    let mut started: HashMap<i64, DateTime<Utc>> = HashMap::new();
+    let mut revisit: Option<JoinHandle<()>> = None;
+    let mut last_revisit: Option<Instant> = None;
+
+    tracing::info!("Daemon started; checking schedules every {}", humantime::format_duration(poll));
+    while !shutdown.is_cancelled() {
+        running.retain(|_, handle| !handle.is_finished());
+        match due_scopes(&storage, &config, &started).await {
+            Ok(due) => {
+                for scope in due {
+                    if running.contains_key(&scope.id) {
+                        tracing::debug!("{} is due but its previous crawl is still running", scope.pattern);
+                        continue;
+                    }
+                    started.insert(scope.id, Utc::now());
+                    let crawl = crawl_scope(config.clone(), scope.pattern, exports.clone(), shutdown.child_token());
+                    running.insert(scope.id, tokio::spawn(crawl));
+                }
+            }
+            Err(e) => tracing::error!("Failed to check scope schedules: {}", e),
+        }
+        let revisit_idle = revisit.as_ref().is_none_or(|handle| handle.is_finished());
+        let revisit_due = last_revisit.is_none_or(|last| last.elapsed() >= config.revisit.interval);
+        if config.revisit.enabled && revisit_idle && revisit_due {
+            last_revisit = Some(Instant::now());
+            let pass = revisit_pass(config.clone(), exports.clone(), shutdown.child_token());
+            revisit = Some(tokio::spawn(pass));
+        }
+        tokio::select! {
+            _ = shutdown.cancelled() => {}
+            _ = tokio::time::sleep(poll) => {}
+        }
+    }
+
+    if !running.is_empty() {
+        tracing::info!("Waiting for {} running crawl(s) to stop", running.len());
+    }
+    for (_, handle) in running {
+        if let Err(e) = handle.await {
+            tracing::error!("Scheduled crawl task failed: {}", e);
+        }
+    }
+    if let Some(handle) = revisit {
+        if let Err(e) = handle.await {
+            tracing::error!("Revisit task failed: {}", e);
+        }
+    }
+    tracing::info!("Daemon stopped");
+    Ok(())
+}
+
+/// Active scopes with a schedule whose next due time has passed. A scheduled scope that
+/// was never crawled is due at once.
+async fn due_scopes(
+    storage: &Storage,
+    config: &Config,
+    started: &HashMap<i64, DateTime<Utc>>,
+) -> Result<Vec<Scope>, CrawlifyError> {
+    let conn = storage.pool.get().await?;
+    let now = Utc::now();
+    let mut due = Vec::new();
+    for scope in Scope::get_active(&conn)? {
+        let settings = Scope::settings(&conn, scope.id)?;
+        let schedule = match ScopeConfig::resolve(config, &scope, &settings) {
+            Ok(scope_config) => scope_config.schedule.value,
+            Err(e) => {
+                tracing::warn!("Not scheduling {}: {}", scope.pattern, e);
+                continue;
+            }
+        };
+        let Some(schedule) = schedule else {
+            continue;
+        };
+        let is_due = match scope.last_crawled_at.max(started.get(&scope.id).copied()) {
+            Some(last) => schedule.next_due(last).is_some_and(|next| next <= now),
+            None => true,
+        };
+        if is_due {
+            due.push(scope);
+        }
+    }
+    Ok(due)
+}
+
+/// Crawls one scope in a run of its own, logging instead of returning failures.
+async fn crawl_scope(config: Config, pattern: String, exports: Arc<ExportTargets>, shutdown: CancellationToken) {
+    tracing::info!("Starting scheduled crawl of {}", pattern);
+    let mut crawler = match Crawler::for_scopes(config, vec![pattern.clone()]).await {
+        Ok(crawler) => crawler.with_shutdown(shutdown).with_exports(exports),
+        Err(e) => {
+            tracing::error!("Failed to start scheduled crawl of {}: {}", pattern, e);
+            return;
+        }
+    };
+    let result = crawler.run(&pattern).await;
+    if let Err(e) = crawler.finish(result.as_ref().err()).await {
+        tracing::error!("Failed to record the crawl run of {}: {}", pattern, e);
+    }
+    match result {
+        Ok(()) if crawler.is_interrupted() => tracing::info!("Scheduled crawl of {} interrupted", pattern),
+        Ok(()) => tracing::info!("Scheduled crawl of {} finished", pattern),
+        Err(e) => tracing::error!("Scheduled crawl of {} failed: {}", pattern, e),
+    }
+}
+
+async fn due_pages(config: &Config) -> Result<Vec<(String, Page, PageFreshness)>, CrawlifyError> {
+    let storage = Storage::new(&config.db_path).await?;
+    let conn = storage.pool.get().await?;
+    freshness::backfill(&conn, &config.revisit)?;
+    freshness::due(&conn, &config.revisit, &Scope::get_active(&conn)?)
+}
+
+/// Revisits the pages due for one in a run of their own, logging instead of returning
+/// failures. Passes with nothing due leave no run behind.
+async fn revisit_pass(config: Config, exports: Arc<ExportTargets>, shutdown: CancellationToken) {
+    let due = match due_pages(&config).await {
+        Ok(due) if due.is_empty() => return,
+        Ok(due) => due,
+        Err(e) => {
+            tracing::error!("Failed to find pages due for a revisit: {}", e);
+            return;
+        }
+    };
+    tracing::info!("Revisiting {} page(s)", due.len());
+    let groups = freshness::by_scope(&due);
+    let patterns = groups.iter().map(|(pattern, _)| pattern.clone()).collect();
+    let mut crawler = match Crawler::for_scopes(config, patterns).await {
+        Ok(crawler) => crawler.with_shutdown(shutdown).with_exports(exports),
+        Err(e) => {
+            tracing::error!("Failed to start revisit: {}", e);
+            return;
+        }
+    };
+    let result = freshness::revisit(&mut crawler, groups).await;
+    if let Err(e) = crawler.finish(result.as_ref().err()).await {
+        tracing::error!("Failed to record the revisit run: {}", e);
+    }
+    match result {
+        Ok(()) if crawler.is_interrupted() => tracing::info!("Revisit interrupted"),
+        Ok(()) => tracing::info!("Revisit finished"),
+        Err(e) => tracing::error!("Revisit failed: {}", e),
+    }
+}
@@ -0,0 +1,97 @@
+use crate::ssrf::SsrfGuard;
+use anyhow::{anyhow, Result};
+use dashmap::DashMap;
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Resolver with static host overrides and a TTL cache in front of the system resolver.
+///
+/// An override may carry a port (`127.0.0.1:8080`); it is used when the URL itself has
+/// no explicit port, so `https://example.com/` can be served by a local mirror.
+/// Overrides are trusted; every other address is checked against the SSRF guard.
+pub struct CachingResolver {
+    overrides: HashMap<String, SocketAddr>,
+    ttl: Duration,
+    cache: Arc<DashMap<String, (Instant, Vec<SocketAddr>)>>,
+    guard: Arc<SsrfGuard>,
+}
+
+/// Parses `ip`, `ip:port` or `[ipv6]:port`; a missing port is left as 0.
+pub fn parse_override(value: &str) -> Result<SocketAddr> {
+    value
+        .parse::<SocketAddr>()
+        .or_else(|_| value.parse::<IpAddr>().map(|ip| SocketAddr::new(ip, 0)))
+        .map_err(|_| anyhow!("invalid host override address '{}', expected IP or IP:port", value))
+}
+
+impl CachingResolver {
+    pub fn new(overrides: &HashMap<String, String>, ttl: Duration, guard: Arc<SsrfGuard>) -> Result<Self> {
+        let overrides = overrides
+            .iter()
+            .map(|(host, addr)| Ok((host.to_ascii_lowercase(), parse_override(addr)?)))
+            .collect::<Result<HashMap<_, _>>>()?;
+        Ok(CachingResolver {
+            overrides,
+            ttl,
+            cache: Arc::new(DashMap::new()),
+            guard,
+        })
+    }
+
+    /// Resolves `host` and checks its addresses against the guard without connecting.
+    /// A proxy resolves the hosts it is asked for itself, out of the guard's sight, so
+    /// proxied requests are checked this way before they are sent.
+    pub async fn check(&self, host: &str) -> std::result::Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let name: Name = host.parse()?;
+        self.resolve(name).await.map(|_| ())
+    }
+}
+
+/// Drops addresses the guard refuses; fails when none are left.
+fn filter_allowed(
+    guard: &SsrfGuard,
+    host: &str,
+    addrs: Vec<SocketAddr>,
+) -> std::result::Result<Addrs, Box<dyn std::error::Error + Send + Sync>> {
+    let (allowed, blocked): (Vec<SocketAddr>, Vec<SocketAddr>) =
+        addrs.into_iter().partition(|addr| guard.is_allowed(host, addr.ip()));
+    if allowed.is_empty() {
+        if let Some(addr) = blocked.first() {
+            return Err(Box::new(guard.check(host, addr.ip()).unwrap_err()));
+        }
+    }
+    Ok(Box::new(allowed.into_iter()))
+}
+
+impl Resolve for CachingResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let host = name.as_str().to_ascii_lowercase();
+
+        if let Some(addr) = self.overrides.get(&host) {
+            let addrs: Addrs = Box::new(std::iter::once(*addr));
+            return Box::pin(std::future::ready(Ok(addrs)));
+        }
+
+        if let Some(entry) = self.cache.get(&host) {
+            let (expires_at, addrs) = entry.value();
+            if *expires_at > Instant::now() {
+                let result = filter_allowed(&self.guard, &host, addrs.clone());
+                return Box::pin(std::future::ready(result));
+            }
+        }
+
+        let cache = self.cache.clone();
+        let ttl = self.ttl;
+        let guard = self.guard.clone();
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host.as_str(), 0)).await?.collect();
+            if !ttl.is_zero() {
+                cache.insert(host.clone(), (Instant::now() + ttl, addrs.clone()));
+            }
+            filter_allowed(&guard, &host, addrs)
+        })
+    }
+}
@@ -24,8 +24,119 @@ pub enum CrawlifyError {
     BB8Rusqlite(#[from] bb8_rusqlite::Error),
     #[error("BB8 Pool error: {0}")]
     BB8Pool(#[from] bb8::RunError<bb8_rusqlite::Error>),
+    #[error("No recorded response in cassette for {0}")]
+    CassetteMiss(String),
+    #[error("Server error {0} for {1}")]
+    ServerError(u16, String),
+    #[error("Unexpected status {0} for {1}")]
+    HttpStatus(u16, String),
+    #[error("All proxies are quarantined")]
+    NoHealthyProxy,
+    #[error("{0}")]
+    SsrfBlocked(String),
+    #[error("Redirect loop at {0}")]
+    RedirectLoop(String),
+    #[error("Too many redirects starting at {0}")]
+    TooManyRedirects(String),
+    #[error("Redirect from {0} to {1} leaves the crawl scope")]
+    RedirectOutOfScope(String, String),
+    #[error("Circuit open for {host}, paused until the cool-down ends")]
+    CircuitOpen { host: String, until: std::time::Instant },
+    #[error("Logging in again after {0} bounced to the login page failed: {1}")]
+    LoginFailed(String, String),
+    #[error("Host {0} is marked dead")]
+    HostDead(String),
+    #[error("Failed to parse {0}")]
+    Parse(String),
+    #[error("Export failed: {0}")]
+    Export(String),
+    #[error("PDF export failed: {0}")]
+    Pdf(String),
+    #[error("Invalid scope {0} in the config file: {1}")]
+    InvalidScope(String, String),
     #[error("Anyhow error: {0}")]
     Anyhow(#[from] anyhow::Error),
     #[error("An unknown error has occurred")]
     Unknown,
-}
\ No newline at end of file
+}
+
+impl CrawlifyError {
+    /// Stable name of the variant, as stored in `fetch_errors.kind`.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            CrawlifyError::Config(_) => "config",
+            CrawlifyError::Database(_) => "database",
+            CrawlifyError::Http(e) if e.is_timeout() => "timeout",
+            CrawlifyError::Http(e) if e.is_connect() => "connect",
+            CrawlifyError::Http(_) => "http",
+            CrawlifyError::Url(_) => "url",
+            CrawlifyError::Io(_) => "io",
+            CrawlifyError::Csv(_) => "csv",
+            CrawlifyError::Xml(_) => "xml",
+            CrawlifyError::SitemapNotFound(_) => "sitemap_not_found",
+            CrawlifyError::Join(_) => "join",
+            CrawlifyError::BB8Rusqlite(_) | CrawlifyError::BB8Pool(_) => "database_pool",
+            CrawlifyError::CassetteMiss(_) => "cassette_miss",
+            CrawlifyError::ServerError(_, _) => "server_error",
+            CrawlifyError::HttpStatus(_, _) => "http_status",
+            CrawlifyError::NoHealthyProxy => "no_healthy_proxy",
+            CrawlifyError::SsrfBlocked(_) => "ssrf_blocked",
+            CrawlifyError::RedirectLoop(_) => "redirect_loop",
+            CrawlifyError::TooManyRedirects(_) => "too_many_redirects",
+            CrawlifyError::RedirectOutOfScope(_, _) => "redirect_out_of_scope",
+            CrawlifyError::CircuitOpen { .. } => "circuit_open",
+            CrawlifyError::LoginFailed(_, _) => "login_failed",
+            CrawlifyError::HostDead(_) => "host_dead",
+            CrawlifyError::Parse(_) => "parse",
+            CrawlifyError::Export(_) => "export",
+            CrawlifyError::Pdf(_) => "pdf",
+            CrawlifyError::InvalidScope(_, _) => "invalid_scope",
+            CrawlifyError::Anyhow(_) => "other",
+            CrawlifyError::Unknown => "unknown",
+        }
+    }
+
+    /// HTTP status behind the error, if there was a response.
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            CrawlifyError::ServerError(status, _) | CrawlifyError::HttpStatus(status, _) => Some(*status),
+            CrawlifyError::Http(e) => e.status().map(|s| s.as_u16()),
+            _ => None,
+        }
+    }
+
+    /// True for failures that may go away on another attempt. Refusals, bad input and
+    /// local faults would fail the same way every time.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            CrawlifyError::Http(e) => !e.is_builder(),
+            CrawlifyError::Io(_)
+            | CrawlifyError::Join(_)
+            | CrawlifyError::BB8Rusqlite(_)
+            | CrawlifyError::BB8Pool(_)
+            | CrawlifyError::ServerError(_, _)
+            | CrawlifyError::NoHealthyProxy
+            | CrawlifyError::LoginFailed(_, _) => true,
+            CrawlifyError::Config(_)
+            | CrawlifyError::Database(_)
+            | CrawlifyError::Url(_)
+            | CrawlifyError::Csv(_)
+            | CrawlifyError::Xml(_)
+            | CrawlifyError::SitemapNotFound(_)
+            | CrawlifyError::HttpStatus(_, _)
+            | CrawlifyError::CassetteMiss(_)
+            | CrawlifyError::SsrfBlocked(_)
+            | CrawlifyError::RedirectLoop(_)
+            | CrawlifyError::TooManyRedirects(_)
+            | CrawlifyError::RedirectOutOfScope(_, _)
+            | CrawlifyError::CircuitOpen { .. }
+            | CrawlifyError::HostDead(_)
+            | CrawlifyError::Parse(_)
+            | CrawlifyError::Export(_)
+            | CrawlifyError::Pdf(_)
+            | CrawlifyError::InvalidScope(_, _)
+            | CrawlifyError::Anyhow(_)
+            | CrawlifyError::Unknown => false,
+        }
+    }
+}
@@ -0,0 +1,45 @@
+use crate::error::CrawlifyError;
+use crate::storage::models::{FetchError, NewFetchError, Scope};
+use crate::storage::Storage;
+use std::sync::Arc;
+use url::Url;
+
+/// Writes crawl failures to the `fetch_errors` table. Recording never fails the crawl;
+/// a failed write is only logged.
+#[derive(Clone)]
+pub struct ErrorLog {
+    db: Storage,
+    run_id: Option<i64>,
+    scopes: Arc<Vec<Scope>>,
+}
+
+impl ErrorLog {
+    pub fn new(db: Storage, run_id: Option<i64>, scopes: Vec<Scope>) -> Self {
+        ErrorLog {
+            db,
+            run_id,
+            scopes: Arc::new(scopes),
+        }
+    }
+
+    /// Records `error` for `url`. `status` is the response status when the failure came
+    /// after a response, e.g. a parse error; otherwise it is taken from the error itself.
+    pub async fn record(&self, url: &Url, error: &CrawlifyError, status: Option<u16>) {
+        let new_error = NewFetchError {
+            run_id: self.run_id,
+            scope_id: self.scopes.iter().find(|s| s.matches(url.as_str())).map(|s| s.id),
+            url: url.to_string(),
+            host: url.host_str().unwrap_or_default().to_string(),
+            kind: error.kind().to_string(),
+            message: error.to_string(),
+            status_code: status.or_else(|| error.status()).map(i32::from),
+        };
+        let result = match self.db.pool.get().await {
+            Ok(conn) => FetchError::create(&conn, &new_error),
+            Err(e) => Err(e.into()),
+        };
+        if let Err(e) = result {
+            tracing::error!("Failed to record error for {}: {}", url, e);
+        }
+    }
+}
@@ -0,0 +1,40 @@
+use crate::storage::models::{Event, EventLevel};
+use crate::storage::Storage;
+use serde_json::Value;
+
+/// Writes crawl lifecycle events to the `events` table. Like the error log, a failed
+/// write is only logged and never fails the crawl.
+#[derive(Clone)]
+pub struct EventLog {
+    db: Storage,
+}
+
+impl EventLog {
+    pub fn new(db: Storage) -> Self {
+        EventLog { db }
+    }
+
+    /// Logs the event through tracing and persists it.
+    pub async fn log(&self, level: EventLevel, message: &str, context: Option<Value>) {
+        let context_text = context.as_ref().map(Value::to_string).unwrap_or_default();
+        match level {
+            EventLevel::Info => tracing::info!("{} {}", message, context_text),
+            EventLevel::Warn => tracing::warn!("{} {}", message, context_text),
+            EventLevel::Error => tracing::error!("{} {}", message, context_text),
+            EventLevel::Debug => tracing::debug!("{} {}", message, context_text),
+        }
+        self.record(level, message, context).await;
+    }
+
+    /// Persists the event without logging it, for events the caller has already logged.
+    pub async fn record(&self, level: EventLevel, message: &str, context: Option<Value>) {
+        let context = context.map(|c| c.to_string());
+        let result = match self.db.pool.get().await {
+            Ok(conn) => Event::create(&conn, level, message, context.as_deref()).map(|_| ()),
+            Err(e) => Err(e.into()),
+        };
+        if let Err(e) = result {
+            tracing::error!("Failed to record event '{}': {}", message, e);
+        }
+    }
+}
@@ -4,6 +4,7 @@ use anyhow::Result;
 use async_trait::async_trait;
 use serde::{Serialize, Deserialize};
 use chrono::{DateTime, Utc};
+use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::fs::File;
 use tokio::io::AsyncWriteExt;
@@ -25,6 +26,12 @@ pub struct ExportPage {
     pub meta_json: Option<String>,
     pub etag: Option<String>,
     pub last_modified: Option<String>,
+    pub warc_filename: Option<String>,
+    pub warc_offset: Option<i64>,
+    pub content_type: Option<String>,
+    pub body_hash: Option<String>,
+    pub redirect_chain: Option<String>,
+    pub run_id: Option<i64>,
     pub created_at: DateTime<Utc>,
     pub outlinks_with_scores: Vec<OutlinkWithScore>,
 }
@@ -44,6 +51,12 @@ impl From<Page> for ExportPage {
             meta_json: page.meta_json,
             etag: page.etag,
             last_modified: page.last_modified,
+            warc_filename: page.warc_filename,
+            warc_offset: page.warc_offset,
+            content_type: page.content_type,
+            body_hash: page.body_hash,
+            redirect_chain: page.redirect_chain,
+            run_id: page.run_id,
             created_at: page.created_at,
             outlinks_with_scores: Vec::new(), // Will be populated separately
         }
@@ -67,26 +80,77 @@ impl JsonlExporter {
         let writer = Arc::new(Mutex::new(tokio::io::BufWriter::new(file)));
         Ok(JsonlExporter { writer })
     }
+
+    /// Opens `path` for appending instead of truncating it.
+    pub async fn append(path: &str) -> Result<Self> {
+        let file = tokio::fs::OpenOptions::new().create(true).append(true).open(path).await?;
+        let writer = Arc::new(Mutex::new(tokio::io::BufWriter::new(file)));
+        Ok(JsonlExporter { writer })
+    }
+
+    /// Appends one line. The write runs in its own task so that aborting the
+    /// exporting task during shutdown cannot leave a partial line behind.
+    async fn write_line(&self, json: String) -> Result<()> {
+        let writer = self.writer.clone();
+        tokio::spawn(async move {
+            let mut writer = writer.lock().await;
+            writer.write_all(format!("{}\n", json).as_bytes()).await?;
+            writer.flush().await
+        })
+        .await??;
+        Ok(())
+    }
+
+    /// Waits for pending writes and flushes the file.
+    pub async fn flush(&self) -> Result<()> {
+        self.writer.lock().await.flush().await?;
+        Ok(())
+    }
+}
+
+/// JSONL exporters by output path, so scopes and concurrent runs exporting to the same
+/// file share one writer.
+#[derive(Default)]
+pub struct ExportTargets {
+    exporters: Mutex<HashMap<String, JsonlExporter>>,
+    append: bool,
+}
+
+impl ExportTargets {
+    /// Targets that truncate each file when it is first opened.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Targets that append to existing files.
+    pub fn appending() -> Self {
+        ExportTargets { append: true, ..Self::default() }
+    }
+
+    /// The exporter writing to `path`, opened on first use.
+    pub async fn get(&self, path: &str) -> Result<JsonlExporter> {
+        let mut exporters = self.exporters.lock().await;
+        if let Some(exporter) = exporters.get(path) {
+            return Ok(exporter.clone());
+        }
+        let exporter = if self.append {
+            JsonlExporter::append(path).await?
+        } else {
+            JsonlExporter::new(path).await?
+        };
+        exporters.insert(path.to_string(), exporter.clone());
+        Ok(exporter)
+    }
 }
 
 #[async_trait]
 impl Exporter for JsonlExporter {
     async fn export(&self, page: &Page) -> Result<()> {
-        let mut writer = self.writer.lock().await;
-        let json = serde_json::to_string(page)?;
-        writer.write_all(json.as_bytes()).await?;
-        writer.write_all(b"\n").await?;
-        writer.flush().await?;
-        Ok(())
+        self.write_line(serde_json::to_string(page)?).await
     }
 
     async fn export_enhanced(&self, page: &ExportPage) -> Result<()> {
-        let mut writer = self.writer.lock().await;
-        let json = serde_json::to_string(page)?;
-        writer.write_all(json.as_bytes()).await?;
-        writer.write_all(b"\n").await?;
-        writer.flush().await?;
-        Ok(())
+        self.write_line(serde_json::to_string(page)?).await
     }
 }
 
@@ -0,0 +1,236 @@
+//! Adaptive revisits. Changes to a page are modelled as a Poisson process whose rate is
+//! estimated from past fetches (Cho and Garcia-Molina's estimator for regular checks),
+//! and each page is revisited about once per expected change: volatile pages every
+//! `min_interval`, stable ones every `max_interval`.
+
+use crate::config::RevisitConfig;
+use crate::crawler::Crawler;
+use crate::error::CrawlifyError;
+use crate::storage::models::{Page, PageFreshness, Scope};
+use chrono::{DateTime, Utc};
+use rusqlite::Connection;
+use std::time::Duration;
+use url::Url;
+
+/// Changes per second estimated from `checks` fetches spread over `elapsed`, `changes`
+/// of which found new content. `None` until the page has been fetched twice.
+pub fn change_rate(checks: i64, changes: i64, elapsed: Duration) -> Option<f64> {
+    if checks == 0 || elapsed.is_zero() {
+        return None;
+    }
+    let n = checks as f64;
+    let x = changes.min(checks) as f64;
+    let mean_interval = elapsed.as_secs_f64() / n;
+    Some(-((n - x + 0.5) / (n + 0.5)).ln() / mean_interval)
+}
+
+/// Delay between the last fetch of a page and its next visit.
+pub fn revisit_interval(freshness: &PageFreshness, config: &RevisitConfig) -> Duration {
+    let elapsed = (freshness.last_fetched_at - freshness.first_fetched_at).to_std().unwrap_or_default();
+    let interval = match change_rate(freshness.checks, freshness.changes, elapsed) {
+        None => config.initial_interval,
+        // Never seen to change: wait twice as long as it has been stable.
+        Some(rate) if rate <= 0.0 => elapsed * 2,
+        Some(rate) => Duration::from_secs_f64((1.0 / rate).min(config.max_interval.as_secs_f64())),
+    };
+    interval.max(config.min_interval).min(config.max_interval)
+}
+
+/// Records a live fetch of the page at `fetched_at`, `changed` if its content differs
+/// from the previous fetch, and schedules its next visit.
+pub fn observe(
+    conn: &Connection,
+    page_id: i64,
+    fetched_at: DateTime<Utc>,
+    changed: bool,
+    config: &RevisitConfig,
+) -> Result<PageFreshness, CrawlifyError> {
+    let freshness = match PageFreshness::find(conn, page_id)? {
+        Some(mut freshness) if fetched_at > freshness.last_fetched_at => {
+            freshness.checks += 1;
+            freshness.changes += changed as i64;
+            freshness.last_fetched_at = fetched_at;
+            scheduled(freshness, config)
+        }
+        // Replayed or reprocessed responses say nothing new about the page.
+        Some(freshness) => return Ok(freshness),
+        None => first_fetch(page_id, fetched_at, config),
+    };
+    freshness.save(conn)?;
+    Ok(freshness)
+}
+
+/// Statistics of a page fetched once, at `fetched_at`.
+fn first_fetch(page_id: i64, fetched_at: DateTime<Utc>, config: &RevisitConfig) -> PageFreshness {
+    let freshness = PageFreshness {
+        page_id,
+        checks: 0,
+        changes: 0,
+        first_fetched_at: fetched_at,
+        last_fetched_at: fetched_at,
+        next_visit_at: fetched_at,
+    };
+    scheduled(freshness, config)
+}
+
+fn scheduled(mut freshness: PageFreshness, config: &RevisitConfig) -> PageFreshness {
+    let interval = chrono::Duration::from_std(revisit_interval(&freshness, config)).unwrap_or(chrono::Duration::MAX);
+    freshness.next_visit_at = freshness.last_fetched_at.checked_add_signed(interval).unwrap_or(DateTime::<Utc>::MAX_UTC);
+    freshness
+}
+
+/// Starts change statistics for pages stored before they were kept, from their last
+/// fetch. Returns how many pages it started.
+pub fn backfill(conn: &Connection, config: &RevisitConfig) -> Result<usize, CrawlifyError> {
+    let pages = PageFreshness::untracked_pages(conn)?;
+    for page in &pages {
+        first_fetch(page.id, page.fetched_at, config).save(conn)?;
+    }
+    Ok(pages.len())
+}
+
+/// Pages of `scopes` due for a revisit, most overdue first and at most `max_pages` of
+/// them, each with the pattern of the scope it belongs to. Reads only: pages stored
+/// before change statistics were kept are estimated from their last fetch until
+/// `backfill` stores their statistics.
+pub fn due(
+    conn: &Connection,
+    config: &RevisitConfig,
+    scopes: &[Scope],
+) -> Result<Vec<(String, Page, PageFreshness)>, CrawlifyError> {
+    let now = Utc::now();
+    let mut candidates = PageFreshness::due(conn, now)?;
+    for page in PageFreshness::untracked_pages(conn)? {
+        let freshness = first_fetch(page.id, page.fetched_at, config);
+        if freshness.next_visit_at <= now {
+            candidates.push((page, freshness));
+        }
+    }
+    candidates.sort_by_key(|(_, freshness)| freshness.next_visit_at);
+    let due = candidates
+        .into_iter()
+        .filter_map(|(page, freshness)| {
+            let scope = scopes.iter().find(|scope| scope.matches(&page.url))?;
+            Some((scope.pattern.clone(), page, freshness))
+        })
+        .take(config.max_pages as usize)
+        .collect();
+    Ok(due)
+}
+
+/// Groups `due` pages by the scope they belong to, keeping the order of first appearance.
+pub fn by_scope(due: &[(String, Page, PageFreshness)]) -> Vec<(String, Vec<Url>)> {
+    let mut groups: Vec<(String, Vec<Url>)> = Vec::new();
+    for (pattern, page, _) in due {
+        let Ok(url) = Url::parse(&page.url) else {
+            tracing::warn!("Not revisiting {}: invalid URL", page.url);
+            continue;
+        };
+        match groups.iter_mut().find(|(p, _)| p == pattern) {
+            Some((_, urls)) => urls.push(url),
+            None => groups.push((pattern.clone(), vec![url])),
+        }
+    }
+    groups
+}
+
+/// Revisits `groups` scope by scope in the run of `crawler`, stopping at the first
+/// failure, interruption or spent budget.
+pub async fn revisit(crawler: &mut Crawler, groups: Vec<(String, Vec<Url>)>) -> anyhow::Result<()> {
+    for (pattern, urls) in groups {
+        crawler.revisit(&pattern, urls).await?;
+        if crawler.is_interrupted() || crawler.budget_limit().is_some() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    const HOUR: u64 = 3600;
+    const DAY: u64 = 24 * HOUR;
+
+    fn config() -> RevisitConfig {
+        RevisitConfig {
+            min_interval: Duration::from_secs(HOUR),
+            max_interval: Duration::from_secs(30 * DAY),
+            initial_interval: Duration::from_secs(DAY),
+            ..Default::default()
+        }
+    }
+
+    fn freshness(checks: i64, changes: i64, elapsed: u64) -> PageFreshness {
+        let first = Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap();
+        PageFreshness {
+            page_id: 1,
+            checks,
+            changes,
+            first_fetched_at: first,
+            last_fetched_at: first + chrono::Duration::seconds(elapsed as i64),
+            next_visit_at: first,
+        }
+    }
+
+    #[test]
+    fn change_rate_needs_a_second_fetch() {
+        assert_eq!(change_rate(0, 0, Duration::from_secs(DAY)), None);
+        assert_eq!(change_rate(3, 1, Duration::ZERO), None);
+    }
+
+    #[test]
+    fn change_rate_of_an_unchanged_page_is_zero() {
+        assert_eq!(change_rate(10, 0, Duration::from_secs(10 * DAY)), Some(0.0));
+    }
+
+    #[test]
+    fn change_rate_grows_with_changes_and_stays_finite() {
+        let elapsed = Duration::from_secs(10 * DAY);
+        let some = change_rate(10, 3, elapsed).unwrap();
+        let most = change_rate(10, 9, elapsed).unwrap();
+        let all = change_rate(10, 10, elapsed).unwrap();
+        assert!(0.0 < some && some < most && most < all);
+        assert!(all.is_finite());
+        // More changes than checks are counted as a change on every check.
+        assert_eq!(change_rate(10, 15, elapsed), Some(all));
+    }
+
+    #[test]
+    fn change_rate_matches_the_estimator() {
+        // -ln((n - x + 0.5) / (n + 0.5)) / (elapsed / n) with n = 4, x = 2 over 4 days.
+        let rate = change_rate(4, 2, Duration::from_secs(4 * DAY)).unwrap();
+        let expected = -(2.5f64 / 4.5).ln() / DAY as f64;
+        assert!((rate - expected).abs() < 1e-12);
+    }
+
+    #[test]
+    fn first_revisit_uses_the_initial_interval() {
+        assert_eq!(revisit_interval(&freshness(0, 0, 0), &config()), Duration::from_secs(DAY));
+        let mut short = config();
+        short.initial_interval = Duration::from_secs(60);
+        assert_eq!(revisit_interval(&freshness(0, 0, 0), &short), Duration::from_secs(HOUR));
+    }
+
+    #[test]
+    fn stable_pages_wait_twice_as_long_up_to_the_maximum() {
+        assert_eq!(revisit_interval(&freshness(3, 0, 2 * DAY), &config()), Duration::from_secs(4 * DAY));
+        assert_eq!(revisit_interval(&freshness(20, 0, 60 * DAY), &config()), Duration::from_secs(30 * DAY));
+    }
+
+    #[test]
+    fn volatile_pages_are_revisited_at_the_minimum_interval() {
+        assert_eq!(revisit_interval(&freshness(10, 10, 10 * 60), &config()), Duration::from_secs(HOUR));
+    }
+
+    #[test]
+    fn interval_is_the_inverse_of_the_change_rate() {
+        let page = freshness(10, 3, 10 * DAY);
+        let rate = change_rate(10, 3, Duration::from_secs(10 * DAY)).unwrap();
+        let interval = revisit_interval(&page, &config());
+        assert!((interval.as_secs_f64() - 1.0 / rate).abs() < 1.0);
+        assert!(interval > Duration::from_secs(DAY) && interval < Duration::from_secs(30 * DAY));
+    }
+}
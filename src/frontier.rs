@@ -3,7 +3,7 @@ use anyhow::Result;
 use dashmap::DashMap;
 use priority_queue::PriorityQueue;
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::time::{Duration, Instant};
 use url::Url;
 
@@ -13,6 +13,8 @@ const MAX_QUEUE_SIZE: usize = 1_000_000;
 pub struct Frontier {
     host_queues: DashMap<String, HostQueue>,
     seen_urls: HashSet<Url>,
+    /// Links followed from the seed to reach each queued or fetched URL.
+    depths: HashMap<Url, u32>,
     size: usize,
 }
 
@@ -43,23 +45,31 @@ fn calculate_priority(url: &Url, _scope: &Scope, is_internal: bool, is_sitemap:
     priority
 }
 
+impl Default for Frontier {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Frontier {
     pub fn new() -> Self {
         Frontier {
             host_queues: DashMap::new(),
             seen_urls: HashSet::new(),
+            depths: HashMap::new(),
             size: 0,
         }
     }
 
-    pub async fn save_state(&self, storage: &crate::storage::Storage) -> Result<()> {
+    /// Checkpoints the frontier of a scope so an interrupted crawl can resume.
+    pub async fn save_state(&self, storage: &crate::storage::Storage, scope_id: i64) -> Result<()> {
         let state = bincode::serialize(self)?;
-        storage.save_frontier_state(&state).await?;
+        storage.save_frontier_state(scope_id, &state).await?;
         Ok(())
     }
 
-    pub async fn load_state(storage: &crate::storage::Storage) -> Result<Option<Self>> {
-        if let Some(state_data) = storage.load_frontier_state().await? {
+    pub async fn load_state(storage: &crate::storage::Storage, scope_id: i64) -> Result<Option<Self>> {
+        if let Some(state_data) = storage.load_frontier_state(scope_id).await? {
             let frontier: Frontier = bincode::deserialize(&state_data)?;
             Ok(Some(frontier))
         } else {
@@ -71,7 +81,8 @@ impl Frontier {
         self.size < MAX_QUEUE_SIZE
     }
 
-    pub fn add_url(&mut self, url: Url, scope: &Scope, is_sitemap: bool) -> Result<()> {
+    /// Queues `url`, found `depth` links away from the seed, unless it was seen before.
+    pub fn add_url(&mut self, url: Url, scope: &Scope, is_sitemap: bool, depth: u32) -> Result<()> {
         if self.seen_urls.contains(&url) || !self.has_capacity() {
             return Ok(());
         }
@@ -89,6 +100,7 @@ impl Frontier {
             });
 
         host_queue.queue.push(url.clone(), priority);
+        self.depths.insert(url.clone(), depth);
         self.seen_urls.insert(url);
         self.size += 1;
 
@@ -125,6 +137,43 @@ impl Frontier {
         }
     }
 
+    /// Puts back a URL that was handed out but could not be fetched yet, or queues one
+    /// from an earlier crawl's retry queue.
+    pub fn requeue(&mut self, url: Url) {
+        let host = url.host_str().unwrap_or_default().to_string();
+        let mut host_queue = self.host_queues.entry(host).or_insert_with(|| HostQueue {
+            queue: PriorityQueue::new(),
+            next_allowed_at: Instant::now(),
+        });
+        self.seen_urls.insert(url.clone());
+        if host_queue.queue.push(url, 0).is_none() {
+            self.size += 1;
+        }
+    }
+
+    /// Holds back every URL of `host` until `until`, e.g. while its circuit is open.
+    pub fn pause_host(&mut self, host: &str, until: Instant) {
+        if let Some(mut host_queue) = self.host_queues.get_mut(host) {
+            host_queue.next_allowed_at = host_queue.next_allowed_at.max(until);
+        }
+    }
+
+    /// Drops every queued URL of `host` and returns them.
+    pub fn drop_host(&mut self, host: &str) -> Vec<Url> {
+        let dropped: Vec<Url> = self
+            .host_queues
+            .remove(host)
+            .map(|(_, q)| q.queue.into_iter().map(|(url, _)| url).collect())
+            .unwrap_or_default();
+        self.size -= dropped.len();
+        dropped
+    }
+
+    /// Depth `url` was queued at; URLs queued from outside the crawl count as seeds.
+    pub fn depth(&self, url: &Url) -> u32 {
+        self.depths.get(url).copied().unwrap_or(0)
+    }
+
     pub fn size(&self) -> usize {
         self.size
     }
@@ -1,9 +1,29 @@
+use crate::auth;
+use crate::cassette::Cassette;
+use crate::circuit_breaker::{Admission, CircuitBreaker, Transition};
+use crate::config::{HttpVersion, LoginConfig};
+use crate::dns::CachingResolver;
+use crate::error::CrawlifyError;
+use crate::login;
+use crate::monitoring::Metrics;
+use crate::proxy_pool::ProxyPool;
+use crate::ssrf::{SsrfBlocked, SsrfGuard};
+use crate::event_log::EventLog;
+use crate::storage::models::EventLevel;
+use crate::warc::{WarcRecordRef, WarcWriter};
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
 use dashmap::DashMap;
-use reqwest::header::{HeaderMap, ACCEPT_ENCODING, USER_AGENT};
-use reqwest::{Client, Response, Result, Url};
+use reqwest::header::{HeaderMap, ACCEPT_ENCODING, LOCATION, USER_AGENT};
+use anyhow::Context;
+use reqwest::{Certificate, Client, ClientBuilder, Identity, Method, Request, StatusCode, Url};
+use reqwest_cookie_store::CookieStoreMutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio_retry::strategy::{ExponentialBackoff, jitter};
-use tokio_retry::Retry;
+use tokio_retry::RetryIf;
 
 const USER_AGENTS: [&str; 5] = [
     "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/109.0.0.0 Safari/537.36",
@@ -13,6 +33,37 @@ const USER_AGENTS: [&str; 5] = [
     "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/108.0.0.0 Safari/537.36",
 ];
 
+/// One redirect followed on the way to the final response.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RedirectHop {
+    pub url: Url,
+    pub status: u16,
+    pub location: Url,
+}
+
+/// A response with its body fully read, whether it came from the network or a cassette.
+#[derive(Debug, Clone)]
+pub struct FetchedResponse {
+    /// Final URL after redirects.
+    pub url: Url,
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Bytes,
+    pub fetched_at: DateTime<Utc>,
+    /// Where the response was archived, when WARC output is enabled.
+    pub warc: Option<WarcRecordRef>,
+    /// Redirects followed to get here, in order; empty if `url` was fetched directly.
+    pub redirects: Vec<RedirectHop>,
+}
+
+impl FetchedResponse {
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+}
+
+type FetchResult = std::result::Result<FetchedResponse, CrawlifyError>;
+
 #[derive(Clone)]
 struct HostState {
     last_request_at: Instant,
@@ -23,42 +74,589 @@ struct HostState {
 pub struct HttpClient {
     client: Client,
     host_states: DashMap<String, HostState>,
+    cookie_store: Option<Arc<CookieStoreMutex>>,
+    cookie_jar_path: Option<String>,
+    login: Option<Arc<LoginConfig>>,
+    /// Counts completed logins so concurrent tasks that hit the same expired
+    /// session trigger only one re-login.
+    login_generation: Arc<tokio::sync::Mutex<u64>>,
+    cassette: Option<Arc<Cassette>>,
+    warc: Option<Arc<WarcWriter>>,
+    proxy_pool: Option<Arc<ProxyPool>>,
+    ssrf: Arc<SsrfGuard>,
+    resolver: Arc<CachingResolver>,
+    /// Requests go through `http.proxy` or the proxy pool, which resolve hosts themselves.
+    proxied: bool,
+    max_redirects: usize,
+    breaker: Arc<CircuitBreaker>,
+    metrics: Option<Arc<Metrics>>,
+    events: Option<EventLog>,
+    /// Lower bound on the politeness delay between requests to one host.
+    request_delay: Option<Duration>,
+}
+
+struct Clients {
+    client: Client,
+    proxy_pool: Option<Arc<ProxyPool>>,
+    ssrf: Arc<SsrfGuard>,
+    resolver: Arc<CachingResolver>,
+}
+
+/// Finds an SSRF refusal from the resolver or redirect policy inside a reqwest error.
+fn ssrf_blocked(err: &reqwest::Error) -> Option<&SsrfBlocked> {
+    let mut source = std::error::Error::source(err);
+    while let Some(e) = source {
+        if let Some(blocked) = e.downcast_ref::<SsrfBlocked>() {
+            return Some(blocked);
+        }
+        source = e.source();
+    }
+    None
 }
 
 impl HttpClient {
-    pub fn new(config: &crate::config::Config) -> Result<Self> {
-        let mut headers = HeaderMap::new();
+    pub fn new(config: &crate::config::Config) -> std::result::Result<Self, CrawlifyError> {
+        let Clients { client, proxy_pool, ssrf, resolver } = Self::build_clients(config, HeaderMap::new(), None)?;
+
+        Ok(HttpClient {
+            client,
+            host_states: DashMap::new(),
+            cookie_store: None,
+            cookie_jar_path: None,
+            login: None,
+            login_generation: Arc::new(tokio::sync::Mutex::new(0)),
+            cassette: Self::cassette(config)?,
+            warc: Self::warc_writer(config)?,
+            proxied: config.http.proxy.is_some() || proxy_pool.is_some(),
+            proxy_pool,
+            ssrf,
+            resolver,
+            max_redirects: config.http.max_redirects,
+            breaker: Arc::new(CircuitBreaker::new(&config.http.circuit_breaker)),
+            metrics: None,
+            events: None,
+            request_delay: None,
+        })
+    }
+
+    /// Builds a client carrying the auth and login settings configured for the scope
+    /// `pattern`, falling back to a plain client when the scope has none.
+    pub fn for_scope(config: &crate::config::Config, pattern: &str) -> anyhow::Result<Self> {
+        let login_config = config.login_for(pattern);
+        let auth_config = match (config.auth_for(pattern), login_config) {
+            (Some(auth_config), _) => auth_config.clone(),
+            (None, Some(_)) => Default::default(),
+            (None, None) => return Ok(Self::new(config)?),
+        };
+        let auth_config = &auth_config;
+
+        let headers = auth::auth_headers(auth_config)?;
+        let mut store = match &auth_config.cookie_jar {
+            Some(path) => auth::load_cookie_jar(path)?,
+            None => cookie_store::CookieStore::default(),
+        };
+        if let Some(cookies_file) = &auth_config.cookies_file {
+            let imported = auth::import_netscape_cookies(&mut store, cookies_file)?;
+            tracing::info!("Imported {} cookies from {}", imported, cookies_file);
+        }
+        let cookie_store = Arc::new(CookieStoreMutex::new(store));
+
+        let Clients { client, proxy_pool, ssrf, resolver } = Self::build_clients(config, headers, Some(cookie_store.clone()))?;
+
+        Ok(HttpClient {
+            client,
+            host_states: DashMap::new(),
+            cookie_store: Some(cookie_store),
+            cookie_jar_path: auth_config.cookie_jar.clone(),
+            login: login_config.cloned().map(Arc::new),
+            login_generation: Arc::new(tokio::sync::Mutex::new(0)),
+            cassette: Self::cassette(config)?,
+            warc: Self::warc_writer(config)?,
+            proxied: config.http.proxy.is_some() || proxy_pool.is_some(),
+            proxy_pool,
+            ssrf,
+            resolver,
+            max_redirects: config.http.max_redirects,
+            breaker: Arc::new(CircuitBreaker::new(&config.http.circuit_breaker)),
+            metrics: None,
+            events: None,
+            request_delay: None,
+        })
+    }
+
+    /// Reports per-proxy request counts and quarantines to `metrics`.
+    pub fn with_metrics(mut self, metrics: Arc<Metrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Builds the default client and, if a proxy pool is configured, one client per proxy.
+    fn build_clients(
+        config: &crate::config::Config,
+        headers: HeaderMap,
+        cookie_store: Option<Arc<CookieStoreMutex>>,
+    ) -> anyhow::Result<Clients> {
+        let proxy_urls: Vec<Url> = config
+            .http
+            .proxy
+            .iter()
+            .chain(config.http.proxy_pool.iter().flat_map(|pool| pool.proxies.iter()))
+            .filter_map(|proxy| Url::parse(proxy).ok())
+            .collect();
+        let ssrf = Arc::new(SsrfGuard::new(
+            &config.http.ssrf,
+            proxy_urls.iter().filter_map(|url| url.host_str()),
+        )?);
+        let resolver = Arc::new(CachingResolver::new(
+            &config.http.host_overrides,
+            config.http.dns_cache_ttl,
+            ssrf.clone(),
+        )?);
+        // Redirects are followed hop by hop in `execute_following`.
+        let build = |proxy: Option<&str>| -> anyhow::Result<Client> {
+            let mut builder = Self::client_builder(config, headers.clone(), proxy)?
+                .dns_resolver(resolver.clone())
+                .redirect(reqwest::redirect::Policy::none());
+            if let Some(cookie_store) = &cookie_store {
+                builder = builder.cookie_provider(cookie_store.clone());
+            }
+            Ok(builder.build()?)
+        };
+
+        let client = build(config.http.proxy.as_deref())?;
+        let proxy_pool = match &config.http.proxy_pool {
+            Some(pool_config) => {
+                let pool = Arc::new(ProxyPool::new(pool_config, |proxy| build(Some(proxy)))?);
+                ProxyPool::spawn_health_checks(&pool);
+                Some(pool)
+            }
+            None => None,
+        };
+        Ok(Clients {
+            client,
+            proxy_pool,
+            ssrf,
+            resolver,
+        })
+    }
+
+    /// Also writes blocked SSRF attempts, circuit breaker transitions and proxy
+    /// quarantines to the `events` table.
+    pub fn with_events(mut self, events: EventLog) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// Waits at least `delay` between two requests to the same host.
+    pub fn with_request_delay(mut self, delay: Option<Duration>) -> Self {
+        self.request_delay = delay;
+        self
+    }
+
+    /// Records a refused destination as an event and turns it into the request error.
+    async fn blocked(&self, url: &Url, blocked: &SsrfBlocked) -> CrawlifyError {
+        if let Some(events) = &self.events {
+            let context = serde_json::json!({
+                "url": url.as_str(),
+                "host": blocked.host,
+                "ip": blocked.ip.to_string(),
+            });
+            events.record(EventLevel::Warn, &blocked.to_string(), Some(context)).await;
+        }
+        CrawlifyError::SsrfBlocked(blocked.to_string())
+    }
+
+    /// Resolves the host of a proxied request through the guard, since the proxy's own
+    /// lookup never reaches it. Hosts that cannot be resolved here are refused as well;
+    /// allowlist them in `http.ssrf.allow` if only the proxy can resolve them.
+    async fn check_proxied(&self, url: &Url) -> std::result::Result<(), CrawlifyError> {
+        let Some(url::Host::Domain(host)) = url.host() else {
+            return Ok(());
+        };
+        if !self.proxied || !self.ssrf.checks_host(host) {
+            return Ok(());
+        }
+        match self.resolver.check(host).await {
+            Ok(()) => Ok(()),
+            Err(e) => match e.downcast_ref::<SsrfBlocked>() {
+                Some(blocked) => Err(self.blocked(url, blocked).await),
+                None => Err(CrawlifyError::SsrfBlocked(format!(
+                    "blocked request to {}: cannot resolve it to check the destination ({})",
+                    host, e
+                ))),
+            },
+        }
+    }
+
+    /// The client to send a request to `url` with, and the index of the pooled proxy it uses.
+    fn select_client(&self, url: &Url) -> std::result::Result<(&Client, Option<usize>), CrawlifyError> {
+        match &self.proxy_pool {
+            Some(pool) => {
+                let index = pool
+                    .select(url.host_str().unwrap_or_default())
+                    .ok_or(CrawlifyError::NoHealthyProxy)?;
+                Ok((pool.client(index), Some(index)))
+            }
+            None => Ok((&self.client, None)),
+        }
+    }
+
+    async fn report_proxy(&self, index: Option<usize>, success: bool, bytes: u64) {
+        let (Some(pool), Some(index)) = (&self.proxy_pool, index) else {
+            return;
+        };
+        let quarantined = pool.report(index, success);
+        let proxy = pool.proxy_url(index);
+        if let Some(metrics) = &self.metrics {
+            metrics.record_proxy_request(proxy, success, bytes);
+            if quarantined {
+                metrics.inc_proxy_quarantines(proxy);
+            }
+        }
+        if let (true, Some(events)) = (quarantined, &self.events) {
+            let context = serde_json::json!({ "proxy": proxy });
+            events.log(EventLevel::Warn, "Proxy quarantined", Some(context)).await;
+        }
+    }
+
+    /// Refuses the request while `url`'s host is paused or dead.
+    fn admit(&self, url: &Url) -> std::result::Result<(), CrawlifyError> {
+        let host = url.host_str().unwrap_or_default();
+        match self.breaker.admit(host) {
+            Admission::Allowed => Ok(()),
+            Admission::Paused(until) => Err(CrawlifyError::CircuitOpen {
+                host: host.to_string(),
+                until,
+            }),
+            Admission::Dead => Err(CrawlifyError::HostDead(host.to_string())),
+        }
+    }
+
+    async fn report_host(&self, url: &Url, success: bool) {
+        let host = url.host_str().unwrap_or_default();
+        let transition = self.breaker.record(host, success);
+        if let (Transition::Tripped, Some(metrics)) = (transition, &self.metrics) {
+            metrics.inc_host_backoffs();
+        }
+        let (level, message) = match transition {
+            Transition::None => return,
+            Transition::Tripped => (EventLevel::Warn, "Circuit breaker tripped"),
+            Transition::Recovered => (EventLevel::Info, "Circuit breaker recovered"),
+            Transition::Died => (EventLevel::Error, "Host marked dead"),
+        };
+        if let Some(events) = &self.events {
+            let context = serde_json::json!({ "host": host });
+            events.log(level, message, Some(context)).await;
+        }
+    }
+
+    /// Hosts the circuit breaker gave up on during this crawl.
+    pub fn dead_hosts(&self) -> Vec<String> {
+        self.breaker.dead_hosts()
+    }
+
+    fn cassette(config: &crate::config::Config) -> std::result::Result<Option<Arc<Cassette>>, CrawlifyError> {
+        match &config.http.cassette {
+            Some(cassette_config) => Ok(Some(Arc::new(Cassette::new(cassette_config)?))),
+            None => Ok(None),
+        }
+    }
+
+    fn warc_writer(config: &crate::config::Config) -> std::result::Result<Option<Arc<WarcWriter>>, CrawlifyError> {
+        match &config.http.warc {
+            Some(warc_config) => Ok(Some(Arc::new(WarcWriter::new(warc_config)?))),
+            None => Ok(None),
+        }
+    }
+
+    pub fn is_replaying(&self) -> bool {
+        self.cassette.as_ref().is_some_and(|c| c.is_replay())
+    }
+
+    /// Performs the scope's form login, if one is configured.
+    pub async fn login(&self) -> anyhow::Result<()> {
+        if self.is_replaying() {
+            return Ok(());
+        }
+        let mut generation = self.login_generation.lock().await;
+        self.login_locked(&mut generation).await
+    }
+
+    async fn login_locked(&self, generation: &mut u64) -> anyhow::Result<()> {
+        if let (Some(login_config), Some(cookie_store)) = (&self.login, &self.cookie_store) {
+            login::login(self, cookie_store, login_config).await?;
+            *generation += 1;
+        }
+        Ok(())
+    }
+
+    /// Logs in again unless another task already did so since `seen_generation`.
+    async fn relogin(&self, seen_generation: u64) -> anyhow::Result<()> {
+        let mut generation = self.login_generation.lock().await;
+        if *generation != seen_generation {
+            return Ok(());
+        }
+        self.login_locked(&mut generation).await
+    }
+
+    fn is_login_bounce(&self, requested: &Url, response: &FetchedResponse) -> bool {
+        match &self.login {
+            Some(login_config) => {
+                !login::is_login_page(login_config, requested) && login::is_login_page(login_config, &response.url)
+            }
+            None => false,
+        }
+    }
+
+    fn client_builder(
+        config: &crate::config::Config,
+        mut headers: HeaderMap,
+        proxy: Option<&str>,
+    ) -> anyhow::Result<ClientBuilder> {
         headers.insert(ACCEPT_ENCODING, "br,gzip,deflate".parse().unwrap());
 
         let mut client_builder = Client::builder()
             .pool_max_idle_per_host(config.http.pool_max_idle_per_host)
             .connect_timeout(config.http.connect_timeout)
             .timeout(config.http.request_timeout)
-            .default_headers(headers);
+            .default_headers(headers)
+            .local_address(config.http.local_address);
+
+        client_builder = match config.http.http_version {
+            HttpVersion::Auto => client_builder,
+            HttpVersion::Http1Only => client_builder.http1_only(),
+            HttpVersion::Http2PriorKnowledge => client_builder.http2_prior_knowledge(),
+        };
+
+        let tls = &config.http.tls;
+        for path in &tls.ca_certs {
+            let pem = std::fs::read(path).with_context(|| format!("failed to read CA certificate {}", path))?;
+            for certificate in Certificate::from_pem_bundle(&pem)? {
+                client_builder = client_builder.add_root_certificate(certificate);
+            }
+        }
+        match (&tls.client_cert, &tls.client_key) {
+            (Some(cert_path), Some(key_path)) => {
+                let cert = std::fs::read(cert_path)
+                    .with_context(|| format!("failed to read client certificate {}", cert_path))?;
+                let key = std::fs::read(key_path).with_context(|| format!("failed to read client key {}", key_path))?;
+                client_builder = client_builder.identity(Identity::from_pkcs8_pem(&cert, &key)?);
+            }
+            (None, None) => {}
+            _ => anyhow::bail!("http.tls.client_cert and http.tls.client_key must be set together"),
+        }
+        if tls.insecure {
+            tracing::warn!("TLS certificate verification is disabled (http.tls.insecure)");
+            client_builder = client_builder
+                .danger_accept_invalid_certs(true)
+                .danger_accept_invalid_hostnames(true);
+        }
 
-        if let Some(proxy_url) = &config.http.proxy {
+        if let Some(proxy_url) = proxy {
             client_builder = client_builder.proxy(reqwest::Proxy::all(proxy_url)?);
         }
 
-        let client = client_builder.build()?;
+        Ok(client_builder)
+    }
 
-        Ok(HttpClient {
-            client,
-            host_states: DashMap::new(),
-        })
+    /// Writes the session cookies back to the scope's persistent cookie jar, if it has one.
+    pub fn save_cookies(&self) -> anyhow::Result<()> {
+        if let (Some(store), Some(path)) = (&self.cookie_store, &self.cookie_jar_path) {
+            let store = store.lock().map_err(|_| anyhow::anyhow!("cookie store lock poisoned"))?;
+            auth::save_cookie_jar(&store, path)?;
+        }
+        Ok(())
     }
 
     pub fn get_client(&self) -> &Client {
         &self.client
     }
 
+    /// Sends `request` and reads the whole body. With a cassette attached, the exchange is
+    /// recorded, or in replay mode answered from the cassette without touching the network.
+    async fn execute(&self, request: Request) -> FetchResult {
+        let method = request.method().clone();
+        let url = request.url().clone();
+
+        if let Some(cassette) = self.cassette.as_ref().filter(|c| c.is_replay()) {
+            return cassette
+                .replay(&method, &url)
+                .await?
+                .ok_or_else(|| CrawlifyError::CassetteMiss(format!("{} {}", method, url)));
+        }
+
+        let request_headers = request.headers().clone();
+        let fetched_at = Utc::now();
+        let start_time = Instant::now();
+        if let Err(blocked) = self.ssrf.check_url(&url) {
+            return Err(self.blocked(&url, &blocked).await);
+        }
+        self.check_proxied(&url).await?;
+        let mut fetched = self.send(request, fetched_at).await?;
+
+        if let Some(warc) = &self.warc {
+            let elapsed_ms = start_time.elapsed().as_millis();
+            let write = {
+                let warc = Arc::clone(warc);
+                let (method, url, request_headers, fetched) =
+                    (method.clone(), url.clone(), request_headers.clone(), fetched.clone());
+                move || warc.write_exchange(&method, &url, &request_headers, &fetched, elapsed_ms)
+            };
+            let written = tokio::task::spawn_blocking(write)
+                .await
+                .map_err(CrawlifyError::from)
+                .and_then(|result| result);
+            match written {
+                Ok(record_ref) => fetched.warc = Some(record_ref),
+                Err(e) => tracing::error!("Failed to write WARC records for {}: {}", url, e),
+            }
+        }
+        if let Some(cassette) = &self.cassette {
+            cassette.record(&method, &url, &request_headers, &fetched).await?;
+        }
+        Ok(fetched)
+    }
+
+    /// Sends the request through the selected client. With a proxy pool, a request whose
+    /// proxy cannot be reached is retried through the next healthy proxy.
+    async fn send(&self, mut request: Request, fetched_at: DateTime<Utc>) -> FetchResult {
+        let attempts = self.proxy_pool.as_ref().map_or(1, |pool| pool.len());
+        let mut attempt = 1;
+        loop {
+            let url = request.url().clone();
+            let (client, proxy) = self.select_client(&url)?;
+            let retry = if attempt < attempts { request.try_clone() } else { None };
+
+            let result = async {
+                let response = client.execute(request).await?;
+                Ok::<_, reqwest::Error>(FetchedResponse {
+                    url: response.url().clone(),
+                    status: response.status(),
+                    headers: response.headers().clone(),
+                    body: response.bytes().await?,
+                    fetched_at,
+                    warc: None,
+                    redirects: Vec::new(),
+                })
+            }
+            .await;
+
+            match result {
+                Ok(response) => {
+                    self.report_proxy(
+                        proxy,
+                        response.status != StatusCode::PROXY_AUTHENTICATION_REQUIRED,
+                        response.body.len() as u64,
+                    )
+                    .await;
+                    return Ok(response);
+                }
+                Err(e) => {
+                    if let Some(blocked) = ssrf_blocked(&e) {
+                        return Err(self.blocked(&url, blocked).await);
+                    }
+                    self.report_proxy(proxy, false, 0).await;
+                    match retry {
+                        Some(retry) if proxy.is_some() && e.is_connect() => {
+                            tracing::warn!("Proxy failed for {}: {}. Trying another proxy", retry.url(), e);
+                            request = retry;
+                            attempt += 1;
+                        }
+                        _ => return Err(e.into()),
+                    }
+                }
+            }
+        }
+    }
+
+    /// Sends `request` and follows redirects one exchange at a time, so every hop is
+    /// archived and checked by the SSRF guard. Each redirect target must also pass
+    /// `allow_hop`; the scope's login page is always allowed so expired sessions are
+    /// still detected. Loops and chains longer than `max_redirects` are errors.
+    async fn execute_following(&self, request: Request, allow_hop: &(dyn Fn(&Url) -> bool + Sync)) -> FetchResult {
+        let mut method = request.method().clone();
+        let user_agent = request.headers().get(USER_AGENT).cloned();
+        let start_url = request.url().clone();
+        // 307 and 308 repeat a POST with its body.
+        let post = (method == Method::POST).then(|| request.try_clone()).flatten();
+        let mut seen = HashSet::from([(method.clone(), start_url.clone())]);
+        let mut redirects = Vec::new();
+        let mut response = self.execute(request).await?;
+
+        while response.status.is_redirection() {
+            let Some(location) = response.headers.get(LOCATION).and_then(|v| v.to_str().ok()) else {
+                break;
+            };
+            let mut next = response.url.join(location)?;
+            next.set_fragment(None);
+            redirects.push(RedirectHop {
+                url: response.url.clone(),
+                status: response.status.as_u16(),
+                location: next.clone(),
+            });
+
+            if redirects.len() > self.max_redirects {
+                return Err(CrawlifyError::TooManyRedirects(start_url.to_string()));
+            }
+            // 303 and the historical 301/302 behaviour turn a POST into a GET, as after
+            // a login form; other methods are kept as is.
+            if method == Method::POST && matches!(response.status.as_u16(), 301..=303) {
+                method = Method::GET;
+            }
+            if !seen.insert((method.clone(), next.clone())) {
+                return Err(CrawlifyError::RedirectLoop(next.to_string()));
+            }
+            let to_login = self.login.as_ref().is_some_and(|config| login::is_login_page(config, &next));
+            if !allow_hop(&next) && !to_login {
+                tracing::warn!("Not following redirect from {} to {}: out of scope", response.url, next);
+                return Err(CrawlifyError::RedirectOutOfScope(response.url.to_string(), next.to_string()));
+            }
+
+            let request = match post.as_ref().filter(|_| method == Method::POST).and_then(Request::try_clone) {
+                Some(mut request) => {
+                    *request.url_mut() = next;
+                    request
+                }
+                None => {
+                    let mut builder = self.client.request(method.clone(), next);
+                    if let Some(user_agent) = &user_agent {
+                        builder = builder.header(USER_AGENT, user_agent.clone());
+                    }
+                    builder.build()?
+                }
+            };
+            response = self.execute(request).await?;
+        }
+
+        response.redirects = redirects;
+        Ok(response)
+    }
+
+    /// Single GET without retries or politeness delays, for robots.txt and sitemaps.
+    pub async fn get(&self, url: &Url) -> FetchResult {
+        self.execute_following(self.client.request(Method::GET, url.clone()).build()?, &|_| true)
+            .await
+    }
+
+    /// Submits a login form. Unlike the crawl requests it follows redirects anywhere, but
+    /// every hop still goes through the SSRF guard.
+    pub(crate) async fn post_form(&self, url: &Url, fields: &[(String, String)]) -> FetchResult {
+        self.execute_following(self.client.post(url.clone()).form(fields).build()?, &|_| true)
+            .await
+    }
+
+    pub async fn head(&self, url: &Url) -> FetchResult {
+        self.execute_following(self.client.request(Method::HEAD, url.clone()).build()?, &|_| true)
+            .await
+    }
+
     pub fn get_random_user_agent(&self) -> &str {
         // Use a simple deterministic approach to avoid Send issues
         let index = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
             .unwrap()
             .as_nanos() as usize % USER_AGENTS.len();
-        &USER_AGENTS[index]
+        USER_AGENTS[index]
     }
 
     pub async fn pre_request_delay(&self, url: &Url) {
@@ -70,7 +668,7 @@ impl HttpClient {
             // Use deterministic jitter based on host name to avoid Send issues
             let host_hash = host.len() % 1000;
             let jitter = Duration::from_millis(500 + host_hash as u64);
-            let required_delay = base_delay + jitter;
+            let required_delay = (base_delay + jitter).max(self.request_delay.unwrap_or_default());
 
             if time_since_last < required_delay {
                 required_delay - time_since_last
@@ -108,22 +706,49 @@ impl HttpClient {
             });
     }
 
+    /// Fetches `url` with politeness delays and retries. Redirects are followed only to
+    /// URLs accepted by `allow_hop`, normally the crawl scopes.
     pub async fn get_with_retry(
         &self,
         storage: &crate::storage::Storage,
         url: &Url,
-    ) -> Result<Response> {
+        allow_hop: &(dyn Fn(&Url) -> bool + Sync),
+    ) -> FetchResult {
+        let seen_generation = *self.login_generation.lock().await;
+        let response = self.fetch_with_retry(storage, url, allow_hop).await?;
+        if !self.is_login_bounce(url, &response) {
+            return Ok(response);
+        }
+
+        tracing::warn!("{} redirected to the login page, logging in again", url);
+        if let Err(e) = self.relogin(seen_generation).await {
+            tracing::error!("Re-login failed for {}: {}", url, e);
+            return Err(CrawlifyError::LoginFailed(url.to_string(), e.to_string()));
+        }
+        self.fetch_with_retry(storage, url, allow_hop).await
+    }
+
+    async fn fetch_with_retry(
+        &self,
+        storage: &crate::storage::Storage,
+        url: &Url,
+        allow_hop: &(dyn Fn(&Url) -> bool + Sync),
+    ) -> FetchResult {
         let retry_strategy = ExponentialBackoff::from_millis(10).map(jitter).take(3);
 
         let url_clone = url.clone();
 
-        Retry::spawn(retry_strategy, || async {
-            self.pre_request_delay(&url_clone).await;
+        RetryIf::spawn(retry_strategy, || async {
+            let replaying = self.is_replaying();
+            if !replaying {
+                self.admit(&url_clone)?;
+                self.pre_request_delay(&url_clone).await;
+            }
 
             let start_time = Instant::now();
             let user_agent = self.get_random_user_agent();
 
-            let page = storage.get_page_by_url(&url_clone.to_string()).await;
+            let page = storage.get_page_by_url(url_clone.as_str()).await;
 
             let mut request = self.client.get(url_clone.clone());
 
@@ -136,26 +761,131 @@ impl HttpClient {
                 }
             }
 
-            let response_result = request.header(USER_AGENT, user_agent).send().await;
+            let response_result = match request.header(USER_AGENT, user_agent).build() {
+                Ok(request) => self.execute_following(request, allow_hop).await,
+                Err(err) => Err(err.into()),
+            };
 
             let rtt = start_time.elapsed();
             self.post_request_update(&url_clone, rtt);
+            if !replaying {
+                // Refusals such as SSRF or off-scope redirects say nothing about the host's health.
+                let host_failed = match &response_result {
+                    Ok(response) => response.status.is_server_error(),
+                    Err(err) => err.is_retryable(),
+                };
+                self.report_host(&url_clone, !host_failed).await;
+            }
 
             match response_result {
                 Ok(response) => {
-                    if response.status().is_server_error() {
-                        let err = response.error_for_status().unwrap_err();
+                    if response.status.is_server_error() {
+                        let err = CrawlifyError::ServerError(response.status.as_u16(), url_clone.to_string());
                         tracing::warn!("Server error for {}: {}. Retrying...", url_clone, err);
                         return Err(err);
                     }
                     Ok(response)
                 }
+                Err(err) if !err.is_retryable() => Err(err),
                 Err(err) => {
                     tracing::warn!("Request error for {}: {}. Retrying...", url_clone, err);
                     Err(err)
                 }
             }
-        })
+        }, CrawlifyError::is_retryable)
         .await
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A config whose `http` section has the required fields plus `http`.
+    fn config(http: &str) -> crate::config::Config {
+        let yaml = format!(
+            "db_path: test.db\nexport_path: test.jsonl\nnlp:\n  enabled: false\n  keywords: []\n\
+             http:\n  connect_timeout: 2s\n  request_timeout: 5s\n  pool_max_idle_per_host: 1\n{}",
+            http
+        );
+        serde_yaml::from_str(&yaml).unwrap()
+    }
+
+    /// Serves `body` as HTML to every request until the returned task is aborted.
+    async fn serve(body: &'static str) -> (Url, tokio::task::JoinHandle<()>) {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+        let server = tokio::spawn(async move {
+            loop {
+                let Ok((mut socket, _)) = listener.accept().await else {
+                    return;
+                };
+                let mut request = Vec::new();
+                let mut buffer = [0u8; 1024];
+                while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+                    match socket.read(&mut buffer).await {
+                        Ok(0) | Err(_) => break,
+                        Ok(n) => request.extend_from_slice(&buffer[..n]),
+                    }
+                }
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                let _ = socket.write_all(response.as_bytes()).await;
+            }
+        });
+        (url, server)
+    }
+
+    #[tokio::test]
+    async fn cassette_replays_recorded_responses_without_network() {
+        let dir = std::env::temp_dir().join(format!("crawlify-cassette-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let (url, server) = serve("<html><title>Recorded</title></html>").await;
+
+        let record = config(&format!("  ssrf:\n    allow: [127.0.0.1]\n  cassette:\n    mode: record\n    dir: {}\n", dir.display()));
+        let recorded = HttpClient::new(&record).unwrap().get(&url).await.unwrap();
+        assert_eq!(recorded.status, StatusCode::OK);
+        server.abort();
+        let _ = server.await;
+
+        let replay = config(&format!("  cassette:\n    mode: replay\n    dir: {}\n", dir.display()));
+        let client = HttpClient::new(&replay).unwrap();
+        assert!(client.is_replaying());
+        let replayed = client.get(&url).await.unwrap();
+        assert_eq!(replayed.url, recorded.url);
+        assert_eq!(replayed.status, recorded.status);
+        assert_eq!(replayed.body, recorded.body);
+        assert_eq!(replayed.headers.get("content-type").unwrap(), "text/html");
+
+        let missing = client.get(&url.join("unrecorded").unwrap()).await.unwrap_err();
+        assert!(matches!(missing, CrawlifyError::CassetteMiss(_)), "{:?}", missing);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn guard_checks_hosts_sent_through_a_proxy() {
+        // Nothing listens on the discard port, so a request that reached the proxy would
+        // fail to connect instead of being refused by the guard.
+        let client = HttpClient::new(&config("  proxy: http://127.0.0.1:9\n")).unwrap();
+        let url = Url::parse("http://localhost/").unwrap();
+        let error = client.get(&url).await.unwrap_err();
+        assert!(matches!(error, CrawlifyError::SsrfBlocked(_)), "{:?}", error);
+
+        let allowed = HttpClient::new(&config("  proxy: http://127.0.0.1:9\n  ssrf:\n    allow: [localhost]\n")).unwrap();
+        let error = allowed.get(&url).await.unwrap_err();
+        assert!(!matches!(error, CrawlifyError::SsrfBlocked(_)), "{:?}", error);
+    }
+
+    #[tokio::test]
+    async fn guard_checks_login_form_posts() {
+        let client = HttpClient::new(&config("")).unwrap();
+        let url = Url::parse("http://localhost/login").unwrap();
+        let fields = [("user".to_string(), "alice".to_string())];
+        let error = client.post_form(&url, &fields).await.unwrap_err();
+        assert!(matches!(error, CrawlifyError::SsrfBlocked(_)), "{:?}", error);
+    }
+}
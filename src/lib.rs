@@ -1,16 +1,38 @@
+pub mod auth;
+pub mod body_store;
+pub mod budget;
+pub mod cassette;
+pub mod circuit_breaker;
 pub mod cli;
 pub mod commands;
 pub mod config;
+pub mod content;
 pub mod crawler;
+pub mod daemon;
 pub mod deduplication;
+pub mod dns;
 pub mod error;
+pub mod error_log;
+pub mod event_log;
 pub mod exporter;
+pub mod freshness;
 pub mod frontier;
 pub mod http;
+pub mod login;
 pub mod monitoring;
 pub mod nlp;
 pub mod parser;
+pub mod proxy_pool;
+pub mod retry_queue;
+pub mod schedule;
+pub mod scope_config;
+pub mod scope_sync;
+pub mod shutdown;
 pub mod sitemap;
+pub mod ssrf;
 pub mod storage;
 pub mod telemetry;
-pub mod pdf_exporter;
\ No newline at end of file
+pub mod warc;
+pub mod pdf_exporter;
+pub mod pdf_text;
+pub mod pipeline;
\ No newline at end of file
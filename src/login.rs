@@ -0,0 +1,130 @@
+use crate::auth::read_env;
+use crate::config::LoginConfig;
+use crate::http::HttpClient;
+use anyhow::{anyhow, bail, Result};
+use html5ever::tokenizer::{BufferQueue, TagKind, Token, TokenSink, TokenSinkResult, Tokenizer, TokenizerOpts};
+use reqwest_cookie_store::CookieStoreMutex;
+use url::Url;
+
+#[derive(Debug, Default)]
+struct LoginForm {
+    action: Option<String>,
+    fields: Vec<(String, String)>,
+    has_password: bool,
+}
+
+#[derive(Default)]
+struct LoginFormSink {
+    forms: Vec<LoginForm>,
+    in_form: bool,
+}
+
+impl TokenSink for LoginFormSink {
+    type Handle = ();
+
+    fn process_token(&mut self, token: Token, _line_num: u64) -> TokenSinkResult<()> {
+        if let Token::TagToken(tag) = token {
+            let attr = |name: &str| {
+                tag.attrs
+                    .iter()
+                    .find(|a| a.name.local.as_ref() == name)
+                    .map(|a| a.value.to_string())
+            };
+            match (tag.kind, tag.name.as_ref()) {
+                (TagKind::StartTag, "form") => {
+                    self.in_form = true;
+                    self.forms.push(LoginForm {
+                        action: attr("action"),
+                        ..Default::default()
+                    });
+                }
+                (TagKind::EndTag, "form") => self.in_form = false,
+                (TagKind::StartTag, "input") if self.in_form => {
+                    if let Some(form) = self.forms.last_mut() {
+                        let input_type = attr("type").unwrap_or_default().to_lowercase();
+                        if input_type == "password" {
+                            form.has_password = true;
+                        } else if input_type == "hidden" {
+                            if let Some(name) = attr("name") {
+                                form.fields.push((name, attr("value").unwrap_or_default()));
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        TokenSinkResult::Continue
+    }
+}
+
+/// Finds the login form on the page: the first form with a password input, else the first form.
+fn find_login_form(html: &str) -> Option<LoginForm> {
+    let mut tokenizer = Tokenizer::new(LoginFormSink::default(), TokenizerOpts::default());
+    let mut buffer_queue = BufferQueue::new();
+    buffer_queue.push_back(html5ever::tendril::StrTendril::from(html));
+    let _ = tokenizer.feed(&mut buffer_queue);
+    tokenizer.end();
+
+    let mut forms = std::mem::take(&mut tokenizer.sink.forms);
+    let index = forms.iter().position(|f| f.has_password).unwrap_or(0);
+    (!forms.is_empty()).then(|| forms.swap_remove(index))
+}
+
+/// Returns true when `final_url` is the configured login page, i.e. a fetch of
+/// some other URL was redirected there because the session expired.
+pub fn is_login_page(config: &LoginConfig, final_url: &Url) -> bool {
+    match Url::parse(&config.url) {
+        Ok(login_url) => login_url.host_str() == final_url.host_str() && login_url.path() == final_url.path(),
+        Err(_) => false,
+    }
+}
+
+/// Runs the login flow: GET the login page, collect its hidden inputs (CSRF tokens),
+/// POST them with the credentials and verify the outcome.
+/// Every request and redirect goes through `client`, so the SSRF guard sees each hop.
+pub async fn login(client: &HttpClient, cookie_store: &CookieStoreMutex, config: &LoginConfig) -> Result<()> {
+    let login_url = Url::parse(&config.url)?;
+    let page = client.get(&login_url).await?;
+    if !page.status.is_success() {
+        bail!("login page {} returned status {}", login_url, page.status);
+    }
+    let html = page.text();
+    let form = find_login_form(&html).ok_or_else(|| anyhow!("no form found on {}", login_url))?;
+
+    let action = match config.form_action.as_deref().or(form.action.as_deref()) {
+        Some(action) if !action.is_empty() => login_url.join(action)?,
+        _ => login_url.clone(),
+    };
+
+    let mut fields = form.fields;
+    fields.retain(|(name, _)| name != &config.username_field && name != &config.password_field);
+    fields.extend(config.extra_fields.iter().map(|(k, v)| (k.clone(), v.clone())));
+    fields.push((config.username_field.clone(), read_env(&config.username_env)?));
+    fields.push((config.password_field.clone(), read_env(&config.password_env)?));
+
+    let response = client.post_form(&action, &fields).await?;
+    let status = response.status;
+    let final_url = response.url;
+    if status.is_client_error() || status.is_server_error() {
+        bail!("login POST to {} failed with status {}", action, status);
+    }
+
+    if let Some(cookie_name) = &config.success_cookie {
+        let store = cookie_store.lock().map_err(|_| anyhow!("cookie store lock poisoned"))?;
+        if !store.matches(&final_url).iter().any(|c| c.name() == cookie_name) {
+            bail!("login did not set the {} cookie", cookie_name);
+        }
+    }
+    if let Some(expected) = &config.success_redirect {
+        if !final_url.as_str().starts_with(expected) {
+            bail!("login ended at {} instead of {}", final_url, expected);
+        }
+    }
+    if config.success_cookie.is_none() && config.success_redirect.is_none() && is_login_page(config, &final_url) {
+        bail!("login ended back on the login page");
+    }
+
+    tracing::info!("Logged in at {}", login_url);
+    Ok(())
+}
@@ -1,37 +1,144 @@
 use clap::Parser;
 use crawlify::cli::Cli;
 use crawlify::cli::Commands;
-use crawlify::config::Config;
+use crawlify::cli::ScopesCommand;
+use crawlify::config::{CassetteConfig, CassetteMode, Config};
 use crawlify::telemetry::{get_subscriber, init_subscriber};
 use std::process;
 
+/// Adds `--resolve HOST=ADDR` entries to the configured host overrides.
+fn add_host_overrides(config: &mut Config, resolve: Vec<String>) {
+    for entry in resolve {
+        let Some((host, addr)) = entry.split_once('=') else {
+            eprintln!("Invalid --resolve '{}', expected HOST=ADDR", entry);
+            process::exit(1);
+        };
+        config.http.host_overrides.insert(host.to_string(), addr.to_string());
+    }
+}
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     let subscriber = get_subscriber("crawlify".into(), "info".into(), || Box::new(std::io::stdout()));
     init_subscriber(subscriber);
 
-    let config = Config::from_path("config.yaml")?;
+    let mut config = Config::from_path("config.yaml")?;
     let cli = Cli::parse();
 
     match cli.command {
-        Commands::Crawl => {
+        Commands::Crawl {
+            record,
+            replay,
+            resolve,
+            max_pages,
+            max_exported,
+            max_depth,
+            max_bytes,
+            max_duration,
+        } => {
+            add_host_overrides(&mut config, resolve);
+            if let Some(dir) = record {
+                config.http.cassette = Some(CassetteConfig { mode: CassetteMode::Record, dir });
+            } else if let Some(dir) = replay {
+                config.http.cassette = Some(CassetteConfig { mode: CassetteMode::Replay, dir });
+            }
+            let budget = &mut config.budget;
+            budget.max_pages = max_pages.or(budget.max_pages);
+            budget.max_exported = max_exported.or(budget.max_exported);
+            budget.max_depth = max_depth.or(budget.max_depth);
+            budget.max_bytes = max_bytes.or(budget.max_bytes);
+            budget.max_duration = max_duration.or(budget.max_duration);
             if let Err(e) = crawlify::commands::crawl(config).await {
                 eprintln!("Application error: {}", e);
                 process::exit(1);
             }
         }
+        Commands::Daemon { poll, resolve } => {
+            add_host_overrides(&mut config, resolve);
+            if let Err(e) = crawlify::commands::daemon(config, poll).await {
+                eprintln!("Daemon error: {}", e);
+                process::exit(1);
+            }
+        }
+        Commands::Revisit { dry_run, resolve } => {
+            add_host_overrides(&mut config, resolve);
+            if let Err(e) = crawlify::commands::revisit(config, dry_run).await {
+                eprintln!("Error revisiting pages: {}", e);
+                process::exit(1);
+            }
+        }
+        Commands::ImportWarc { files } => {
+            if let Err(e) = crawlify::commands::import_warc(config, files).await {
+                eprintln!("Error importing WARC files: {}", e);
+                process::exit(1);
+            }
+        }
+        Commands::Reprocess => {
+            if let Err(e) = crawlify::commands::reprocess(config).await {
+                eprintln!("Error reprocessing pages: {}", e);
+                process::exit(1);
+            }
+        }
+        Commands::History { url } => {
+            if let Err(e) = crawlify::commands::history(config, url).await {
+                eprintln!("Error listing page history: {}", e);
+                process::exit(1);
+            }
+        }
+        Commands::Diff { url, from, to } => {
+            if let Err(e) = crawlify::commands::diff(config, url, from, to).await {
+                eprintln!("Error diffing page versions: {}", e);
+                process::exit(1);
+            }
+        }
+        Commands::Errors { run } => {
+            if let Err(e) = crawlify::commands::errors(config, run).await {
+                eprintln!("Error listing fetch errors: {}", e);
+                process::exit(1);
+            }
+        }
+        Commands::Runs { limit, compare } => {
+            if let Err(e) = crawlify::commands::runs(config, limit, compare).await {
+                eprintln!("Error listing crawl runs: {}", e);
+                process::exit(1);
+            }
+        }
+        Commands::Events { level, since, until, grep, limit } => {
+            if let Err(e) = crawlify::commands::events(config, level, since, until, grep, limit).await {
+                eprintln!("Error listing events: {}", e);
+                process::exit(1);
+            }
+        }
+        Commands::DeadLetters => {
+            if let Err(e) = crawlify::commands::dead_letters(config).await {
+                eprintln!("Error listing dead letters: {}", e);
+                process::exit(1);
+            }
+        }
+        Commands::Retry { ids, all } => {
+            if let Err(e) = crawlify::commands::retry_dead_letters(config, ids, all).await {
+                eprintln!("Error retrying dead letters: {}", e);
+                process::exit(1);
+            }
+        }
         Commands::Init => {
             if let Err(e) = crawlify::commands::init(config).await {
                 eprintln!("Error initializing database: {}", e);
                 process::exit(1);
             }
         }
-        Commands::Scopes => {
+        Commands::Scopes { command: None } => {
             if let Err(e) = crawlify::commands::list_scopes(config).await {
                 eprintln!("Error listing scopes: {}", e);
                 process::exit(1);
             }
         }
+        Commands::Scopes { command: Some(ScopesCommand::Sync { dry_run, yes }) } => {
+            if let Err(e) = crawlify::commands::sync_scopes(config, dry_run, yes).await {
+                eprintln!("Error syncing scopes: {}", e);
+                process::exit(1);
+            }
+        }
         Commands::Add { pattern } => {
             if let Err(e) = crawlify::commands::add_scope(config, pattern).await {
                 eprintln!("Error adding scope: {}", e);
@@ -44,6 +151,18 @@ async fn main() -> anyhow::Result<()> {
                 process::exit(1);
             }
         }
+        Commands::Enable { id } => {
+            if let Err(e) = crawlify::commands::set_scope_active(config, id, true).await {
+                eprintln!("Error enabling scope: {}", e);
+                process::exit(1);
+            }
+        }
+        Commands::Disable { id } => {
+            if let Err(e) = crawlify::commands::set_scope_active(config, id, false).await {
+                eprintln!("Error disabling scope: {}", e);
+                process::exit(1);
+            }
+        }
         Commands::Set { id, property, value } => {
             if let Err(e) = crawlify::commands::set_scope(config, id, property, value).await {
                 eprintln!("Error setting scope property: {}", e);
@@ -1,15 +1,32 @@
+use dashmap::{DashMap, DashSet};
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::time;
 
+#[derive(Default)]
+pub struct ProxyMetrics {
+    pub requests: AtomicU64,
+    pub failures: AtomicU64,
+    pub bytes_in: AtomicU64,
+    pub quarantines: AtomicU64,
+}
+
 #[derive(Default)]
 pub struct Metrics {
     pub requests_total: AtomicU64,
     pub bytes_in_total: AtomicU64,
     pub host_backoffs: AtomicU64,
+    pub pages_fetched: AtomicU64,
+    pub pages_exported: AtomicU64,
+    pub pages_duplicate: AtomicU64,
+    /// URLs whose latest fetch or processing failed. Discovery and export fetch the
+    /// same URLs, so failures are counted per URL rather than per attempt.
+    pub failed_urls: DashSet<String>,
     pub frontier_depth: AtomicU64,
     pub mem_rss_mb: AtomicU64,
+    /// Keyed by proxy URL.
+    pub proxies: DashMap<String, ProxyMetrics>,
 }
 
 impl Metrics {
@@ -29,6 +46,27 @@ impl Metrics {
         self.host_backoffs.fetch_add(1, Ordering::Relaxed);
     }
 
+    pub fn inc_pages_fetched(&self, url: &str) {
+        self.pages_fetched.fetch_add(1, Ordering::Relaxed);
+        self.failed_urls.remove(url);
+    }
+
+    pub fn inc_pages_exported(&self) {
+        self.pages_exported.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn inc_pages_duplicate(&self) {
+        self.pages_duplicate.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn mark_failed(&self, url: &str) {
+        self.failed_urls.insert(url.to_string());
+    }
+
+    pub fn pages_failed(&self) -> u64 {
+        self.failed_urls.len() as u64
+    }
+
     pub fn set_frontier_depth(&self, depth: u64) {
         self.frontier_depth.store(depth, Ordering::Relaxed);
     }
@@ -36,6 +74,23 @@ impl Metrics {
     pub fn set_mem_rss(&self, mem_mb: u64) {
         self.mem_rss_mb.store(mem_mb, Ordering::Relaxed);
     }
+
+    pub fn record_proxy_request(&self, proxy: &str, success: bool, bytes: u64) {
+        let stats = self.proxies.entry(proxy.to_string()).or_default();
+        stats.requests.fetch_add(1, Ordering::Relaxed);
+        stats.bytes_in.fetch_add(bytes, Ordering::Relaxed);
+        if !success {
+            stats.failures.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub fn inc_proxy_quarantines(&self, proxy: &str) {
+        self.proxies
+            .entry(proxy.to_string())
+            .or_default()
+            .quarantines
+            .fetch_add(1, Ordering::Relaxed);
+    }
 }
 
 pub struct Monitor {
@@ -59,6 +114,8 @@ impl Monitor {
         let requests = self.metrics.requests_total.load(Ordering::Relaxed);
         let bytes = self.metrics.bytes_in_total.load(Ordering::Relaxed);
         let backoffs = self.metrics.host_backoffs.load(Ordering::Relaxed);
+        let exported = self.metrics.pages_exported.load(Ordering::Relaxed);
+        let failed = self.metrics.pages_failed();
         let depth = self.metrics.frontier_depth.load(Ordering::Relaxed);
         let mem = self.metrics.mem_rss_mb.load(Ordering::Relaxed);
 
@@ -66,9 +123,23 @@ impl Monitor {
             requests,
             bytes_in = bytes,
             host_backoffs = backoffs,
+            pages_exported = exported,
+            pages_failed = failed,
             frontier_depth = depth,
             mem_rss_mb = mem,
             "Crawl Stats"
         );
+
+        for entry in self.metrics.proxies.iter() {
+            let stats = entry.value();
+            tracing::info!(
+                proxy = %entry.key(),
+                requests = stats.requests.load(Ordering::Relaxed),
+                failures = stats.failures.load(Ordering::Relaxed),
+                bytes_in = stats.bytes_in.load(Ordering::Relaxed),
+                quarantines = stats.quarantines.load(Ordering::Relaxed),
+                "Proxy Stats"
+            );
+        }
     }
 }
\ No newline at end of file
@@ -14,7 +14,7 @@ impl KeywordMatcher {
         Ok(KeywordMatcher { ac })
     }
 
-    pub fn find<'a>(&self, text: &'a str) -> bool {
+    pub fn find(&self, text: &str) -> bool {
         self.ac.is_match(text)
     }
 }
@@ -76,7 +76,7 @@ impl NlpProcessor {
     }
 
     /// Score multiple outlinks and return updated vector with scores
-    pub fn score_outlinks(&self, outlinks: &mut Vec<crate::parser::OutlinkWithScore>) {
+    pub fn score_outlinks(&self, outlinks: &mut [crate::parser::OutlinkWithScore]) {
         for outlink in outlinks.iter_mut() {
             outlink.nlp_score = self.score_outlink(&outlink.url);
         }
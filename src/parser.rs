@@ -147,7 +147,7 @@ impl<'a> html5ever::tokenizer::TokenSink for PageDataSink<'a> {
                             if let (Some(prop), Some(cont)) = (property, content) {
                                 self.structured_data
                                     .entry(prop)
-                                    .or_insert_with(Vec::new)
+                                    .or_default()
                                     .push(cont);
                             }
                         } else if tag_name == "link" {
@@ -185,7 +185,7 @@ impl<'a> html5ever::tokenizer::TokenSink for PageDataSink<'a> {
                             if let Ok(json) = serde_json::from_str::<serde_json::Value>(&self.json_ld_content) {
                                 self.structured_data
                                     .entry("json-ld".to_string())
-                                    .or_insert_with(Vec::new)
+                                    .or_default()
                                     .push(serde_json::to_string(&json).unwrap_or_default());
                             }
                             self.json_ld_content.clear();
@@ -0,0 +1,72 @@
+use anyhow::{anyhow, Result};
+use pdf_extract::{Document, Object, OutputError, PlainTextOutput};
+
+/// Text and document metadata extracted from a PDF body.
+#[derive(Debug, Clone, Default)]
+pub struct PdfDocument {
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub page_count: usize,
+    pub text: String,
+}
+
+/// Extracts text and metadata from an in-memory PDF.
+/// The extractor panics on some malformed files, so the work runs behind `catch_unwind`.
+pub fn extract(body: &[u8]) -> Result<PdfDocument> {
+    std::panic::catch_unwind(|| extract_inner(body))
+        .map_err(|_| anyhow!("PDF extractor panicked"))?
+}
+
+fn extract_inner(body: &[u8]) -> Result<PdfDocument> {
+    let mut doc = Document::load_mem(body)?;
+    if doc.is_encrypted() {
+        doc.decrypt("")?;
+    }
+
+    let mut text = String::new();
+    {
+        let mut output = PlainTextOutput::new(&mut text);
+        pdf_extract::output_doc(&doc, &mut output).map_err(|e: OutputError| anyhow!("{}", e))?;
+    }
+
+    Ok(PdfDocument {
+        title: info_string(&doc, b"Title"),
+        author: info_string(&doc, b"Author"),
+        page_count: doc.get_pages().len(),
+        text: normalize_whitespace(&text),
+    })
+}
+
+fn info_string(doc: &Document, key: &[u8]) -> Option<String> {
+    let info = doc.trailer.get(b"Info").ok()?;
+    let (_, info) = doc.dereference(info).ok()?;
+    let value = info.as_dict().ok()?.get(key).ok()?;
+    let (_, value) = doc.dereference(value).ok()?;
+    match value {
+        Object::String(bytes, _) => Some(decode_pdf_string(bytes)).filter(|s| !s.is_empty()),
+        _ => None,
+    }
+}
+
+/// PDF text strings are UTF-16BE when they start with a BOM, PDFDocEncoding (close enough
+/// to Latin-1 for metadata) otherwise.
+fn decode_pdf_string(bytes: &[u8]) -> String {
+    let decoded = if let Some(utf16) = bytes.strip_prefix(&[0xFE, 0xFF]) {
+        let units: Vec<u16> = utf16
+            .chunks_exact(2)
+            .map(|c| u16::from_be_bytes([c[0], c[1]]))
+            .collect();
+        String::from_utf16_lossy(&units)
+    } else {
+        bytes.iter().map(|&b| b as char).collect()
+    };
+    decoded.trim().to_string()
+}
+
+fn normalize_whitespace(text: &str) -> String {
+    text.lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
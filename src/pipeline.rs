@@ -0,0 +1,233 @@
+use crate::body_store::BodyStore;
+use crate::config::{Config, ContentConfig, RevisitConfig};
+use crate::content;
+use crate::deduplication::Deduplicator;
+use crate::error::CrawlifyError;
+use crate::error_log::ErrorLog;
+use crate::exporter::{ExportPage, Exporter, JsonlExporter};
+use crate::freshness;
+use crate::http::FetchedResponse;
+use crate::nlp::NlpProcessor;
+use crate::parser::PageData;
+use crate::pdf_exporter::PdfExporter;
+use crate::storage::connection::DB;
+use crate::storage::models::{NewPage, Page, PageVersion};
+use anyhow::Result;
+use reqwest::header::{CONTENT_TYPE, ETAG, LAST_MODIFIED};
+use reqwest::StatusCode;
+use std::sync::Arc;
+use url::Url;
+
+/// What happened to a response after it went through the pipeline.
+#[derive(Debug)]
+pub enum PageOutcome {
+    /// Not a successful response, or a content type that is denied or cannot be parsed.
+    /// Failures are written to the error log.
+    Skipped,
+    /// The server confirmed that the stored copy is current.
+    NotModified,
+    /// NLP filtering rejected the content.
+    NoMatch,
+    /// Stored without extracted text (images, archives, ...), so not filtered or exported.
+    Recorded(Page),
+    Duplicate(Page),
+    Exported(Page),
+}
+
+/// Turns fetched responses into stored and exported pages: content dispatch, NLP
+/// filtering, hashing, dedup and export. Shared by live crawls and offline sources.
+#[derive(Clone)]
+pub struct Pipeline {
+    db: DB,
+    exporter: Arc<JsonlExporter>,
+    pdf_exporter: Option<Arc<PdfExporter>>,
+    nlp_processor: Arc<NlpProcessor>,
+    deduplicator: Arc<Deduplicator>,
+    body_store: Option<BodyStore>,
+    error_log: Option<ErrorLog>,
+    run_id: Option<i64>,
+    freshness: Option<Arc<RevisitConfig>>,
+}
+
+impl Pipeline {
+    /// `pdf_exporter` renders pages through a live browser; pass `None` for offline sources.
+    pub fn new(config: &Config, db: DB, exporter: JsonlExporter, pdf_exporter: Option<PdfExporter>) -> Result<Self> {
+        Ok(Pipeline {
+            nlp_processor: Arc::new(NlpProcessor::new(&config.nlp)?),
+            deduplicator: Arc::new(Deduplicator::new(db.clone())),
+            body_store: config.store_bodies.then(|| BodyStore::new(db.clone())),
+            db,
+            exporter: Arc::new(exporter),
+            pdf_exporter: pdf_exporter.map(Arc::new),
+            error_log: None,
+            run_id: None,
+            freshness: None,
+        })
+    }
+
+    /// Records parse, export and PDF failures to `error_log`.
+    pub fn with_error_log(mut self, error_log: ErrorLog) -> Self {
+        self.error_log = Some(error_log);
+        self
+    }
+
+    /// Links stored pages to the crawl run `run_id`.
+    pub fn with_run(mut self, run_id: i64) -> Self {
+        self.run_id = Some(run_id);
+        self
+    }
+
+    /// Keeps change statistics of live fetches and schedules revisits with `config`.
+    pub fn with_freshness(mut self, config: RevisitConfig) -> Self {
+        self.freshness = Some(Arc::new(config));
+        self
+    }
+
+    /// Writes `error` to the error log, if there is one.
+    pub async fn record_error(&self, url: &Url, error: &CrawlifyError, status: Option<u16>) {
+        if let Some(error_log) = &self.error_log {
+            error_log.record(url, error, status).await;
+        }
+    }
+
+    /// Parses the response according to its content type and scores its outlinks.
+    /// Returns `None` for 304 responses and denied content types, and an error for any
+    /// other unsuccessful status.
+    pub async fn extract(
+        &self,
+        url: &Url,
+        response: &FetchedResponse,
+        content_rules: &ContentConfig,
+    ) -> Result<Option<PageData>, CrawlifyError> {
+        if response.status == StatusCode::NOT_MODIFIED {
+            return Ok(None);
+        }
+        if !response.status.is_success() {
+            return Err(CrawlifyError::HttpStatus(response.status.as_u16(), url.to_string()));
+        }
+        let content_type = content::content_type(&response.headers);
+        let Some(mut page_data) = content::dispatch(content_type.as_deref(), &response.body, url, content_rules).await? else {
+            return Ok(None);
+        };
+        self.nlp_processor.score_outlinks(&mut page_data.outlinks_with_scores);
+        Ok(Some(page_data))
+    }
+
+    /// Records the page and, if it passes NLP filtering and dedup, exports it. Pages are
+    /// recorded before filtering so their stored bodies can be reprocessed later. A page
+    /// that cannot be written to the export file is an error.
+    /// Redirected pages are stored under their final URL, with `requested` and every
+    /// intermediate hop kept as aliases.
+    pub async fn process(
+        &self,
+        requested: &Url,
+        response: &FetchedResponse,
+        content_rules: &ContentConfig,
+    ) -> Result<PageOutcome> {
+        let url = &response.url;
+        let status = Some(response.status.as_u16());
+        if response.status == StatusCode::NOT_MODIFIED {
+            if let Some(config) = &self.freshness {
+                let page = match self.db.get_page_by_url(url.as_str()).await? {
+                    Some(page) => Some(page),
+                    None => self.db.get_page_by_url(requested.as_str()).await?,
+                };
+                if let Some(page) = page {
+                    freshness::observe(&*self.db.pool.get().await?, page.id, response.fetched_at, false, config)?;
+                }
+            }
+            return Ok(PageOutcome::NotModified);
+        }
+        let processed_page_data = match self.extract(url, response, content_rules).await {
+            Ok(Some(page_data)) => page_data,
+            Ok(None) => return Ok(PageOutcome::Skipped),
+            Err(e) => {
+                tracing::warn!("{}", e);
+                self.record_error(url, &e, status).await;
+                return Ok(PageOutcome::Skipped);
+            }
+        };
+
+        let content_type = content::content_type(&response.headers);
+        let extracted = content::extracts_text(content_type.as_deref(), &response.body);
+        let body_hash = match &self.body_store {
+            Some(store) => Some(store.put(&response.body).await?),
+            None => None,
+        };
+        // Pages without text would all share one hash and dedup against each other.
+        let text_hash = extracted.then(|| crate::deduplication::text_hash(&processed_page_data.main_content));
+        let sim_hash = extracted.then(|| crate::deduplication::sim_hash(&processed_page_data.main_content));
+        let header = |name| response.headers.get(name).and_then(|v| v.to_str().ok()).map(String::from);
+        let new_page = NewPage {
+            url: url.to_string(),
+            canonical_url: processed_page_data.canonical_url.clone(),
+            title: processed_page_data.title.clone(),
+            text_hash,
+            sim_hash,
+            fetched_at: Some(response.fetched_at),
+            status_code: Some(response.status.as_u16() as i32),
+            content_length: Some(response.body.len() as i64),
+            meta_json: Some(serde_json::to_string(&processed_page_data.structured_data).unwrap_or_default()),
+            etag: header(ETAG),
+            last_modified: header(LAST_MODIFIED),
+            warc_filename: response.warc.as_ref().map(|w| w.filename.clone()),
+            warc_offset: response.warc.as_ref().map(|w| w.offset as i64),
+            content_type: header(CONTENT_TYPE),
+            body_hash,
+            redirect_chain: (!response.redirects.is_empty())
+                .then(|| serde_json::to_string(&response.redirects))
+                .transpose()?,
+            run_id: self.run_id,
+        };
+        let page = Page::create(&self.db, &new_page).await?;
+        {
+            let conn = self.db.pool.get().await?;
+            let hops = response.redirects.iter().map(|hop| &hop.url);
+            for alias in std::iter::once(requested).chain(hops).filter(|alias| *alias != url) {
+                page.add_alias(&conn, alias.as_str())?;
+            }
+            if !extracted {
+                return Ok(PageOutcome::Recorded(page));
+            }
+            let changed = PageVersion::record(&conn, &page, &processed_page_data.main_content)?;
+            if changed {
+                tracing::debug!("Recorded new version of {}", url);
+            }
+            if let Some(config) = &self.freshness {
+                freshness::observe(&conn, page.id, page.fetched_at, changed, config)?;
+            }
+        }
+
+        // NLP filtering for export phase
+        if !self.nlp_processor.is_match(&processed_page_data.main_content) {
+            return Ok(PageOutcome::NoMatch);
+        }
+
+        if self.deduplicator.is_duplicate(&page).await {
+            return Ok(PageOutcome::Duplicate(page));
+        }
+
+        // Create enhanced export page with outlinks_with_scores
+        let mut enhanced_export_page = ExportPage::from(page.clone());
+        enhanced_export_page.outlinks_with_scores = processed_page_data.outlinks_with_scores.clone();
+
+        if let Err(e) = self.exporter.export_enhanced(&enhanced_export_page).await {
+            let error = CrawlifyError::Export(e.to_string());
+            self.record_error(url, &error, status).await;
+            return Err(error.into());
+        }
+
+        if let Some(pdf_exporter) = &self.pdf_exporter {
+            match pdf_exporter.export_page_to_pdf(&page).await {
+                Ok(pdf_filename) => tracing::info!("Exporting as pdf name: {}", pdf_filename),
+                Err(e) => {
+                    tracing::error!("Failed to export PDF for {}", url);
+                    self.record_error(url, &CrawlifyError::Pdf(e.to_string()), status).await;
+                }
+            }
+        }
+
+        tracing::info!("Exported page: {}", url);
+        Ok(PageOutcome::Exported(page))
+    }
+}
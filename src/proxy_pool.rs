@@ -0,0 +1,183 @@
+use crate::config::{ProxyPoolConfig, ProxyRotation};
+use dashmap::DashMap;
+use rand::Rng;
+use reqwest::{Client, Url};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant};
+
+#[derive(Default)]
+struct ProxyHealth {
+    consecutive_failures: u32,
+    quarantined_until: Option<Instant>,
+}
+
+struct PooledProxy {
+    url: String,
+    client: Client,
+    health: Mutex<ProxyHealth>,
+}
+
+/// A set of egress proxies, each with its own `Client` since reqwest binds proxies per
+/// client. Healthy proxies are handed out by rotation, optionally sticky per host.
+pub struct ProxyPool {
+    proxies: Vec<PooledProxy>,
+    rotation: ProxyRotation,
+    sticky_hosts: bool,
+    max_failures: u32,
+    quarantine: Duration,
+    check_url: Option<Url>,
+    next: AtomicUsize,
+    assignments: DashMap<String, usize>,
+}
+
+impl ProxyPool {
+    /// `build_client` returns a client configured to go through the given proxy URL.
+    pub fn new(config: &ProxyPoolConfig, build_client: impl Fn(&str) -> anyhow::Result<Client>) -> anyhow::Result<Self> {
+        if config.proxies.is_empty() {
+            anyhow::bail!("proxy_pool.proxies must not be empty");
+        }
+        let proxies = config
+            .proxies
+            .iter()
+            .map(|url| {
+                Ok(PooledProxy {
+                    url: url.clone(),
+                    client: build_client(url)?,
+                    health: Mutex::new(ProxyHealth::default()),
+                })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        Ok(ProxyPool {
+            proxies,
+            rotation: config.rotation,
+            sticky_hosts: config.sticky_hosts,
+            max_failures: config.max_failures.max(1),
+            quarantine: config.quarantine,
+            check_url: config.check_url.as_deref().map(Url::parse).transpose()?,
+            next: AtomicUsize::new(0),
+            assignments: DashMap::new(),
+        })
+    }
+
+    fn health(&self, index: usize) -> std::sync::MutexGuard<'_, ProxyHealth> {
+        self.proxies[index].health.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn is_healthy(&self, index: usize) -> bool {
+        self.health(index).quarantined_until.is_none()
+    }
+
+    /// Picks the proxy for a request to `host`, or `None` if every proxy is quarantined.
+    pub fn select(&self, host: &str) -> Option<usize> {
+        let healthy: Vec<usize> = (0..self.proxies.len()).filter(|&i| self.is_healthy(i)).collect();
+        if healthy.is_empty() {
+            return None;
+        }
+
+        if self.sticky_hosts {
+            if let Some(index) = self.assignments.get(host).map(|i| *i) {
+                if healthy.contains(&index) {
+                    return Some(index);
+                }
+            }
+        }
+
+        let index = match self.rotation {
+            ProxyRotation::RoundRobin => healthy[self.next.fetch_add(1, Ordering::Relaxed) % healthy.len()],
+            ProxyRotation::Random => healthy[rand::thread_rng().gen_range(0..healthy.len())],
+        };
+        if self.sticky_hosts {
+            self.assignments.insert(host.to_string(), index);
+        }
+        Some(index)
+    }
+
+    pub fn len(&self) -> usize {
+        self.proxies.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.proxies.is_empty()
+    }
+
+    pub fn client(&self, index: usize) -> &Client {
+        &self.proxies[index].client
+    }
+
+    pub fn proxy_url(&self, index: usize) -> &str {
+        &self.proxies[index].url
+    }
+
+    /// Records the outcome of a request. Returns true when this failure put the proxy
+    /// into quarantine.
+    pub fn report(&self, index: usize, success: bool) -> bool {
+        let mut health = self.health(index);
+        if success {
+            health.consecutive_failures = 0;
+            return false;
+        }
+        health.consecutive_failures += 1;
+        if health.quarantined_until.is_none() && health.consecutive_failures >= self.max_failures {
+            health.quarantined_until = Some(Instant::now() + self.quarantine);
+            tracing::warn!(
+                "Quarantining proxy {} for {:?} after {} failures",
+                self.proxies[index].url,
+                self.quarantine,
+                health.consecutive_failures
+            );
+            return true;
+        }
+        false
+    }
+
+    /// Re-checks quarantined proxies whose quarantine has run out: a successful request
+    /// to `check_url` through the proxy releases it, a failed one extends the quarantine.
+    pub async fn recheck(&self) {
+        let now = Instant::now();
+        for (index, proxy) in self.proxies.iter().enumerate() {
+            let due = self.health(index).quarantined_until.is_some_and(|until| until <= now);
+            if !due {
+                continue;
+            }
+
+            let healthy = match &self.check_url {
+                Some(check_url) => match proxy.client.get(check_url.clone()).send().await {
+                    Ok(response) => !response.status().is_server_error()
+                        && response.status() != reqwest::StatusCode::PROXY_AUTHENTICATION_REQUIRED,
+                    Err(_) => false,
+                },
+                None => true,
+            };
+
+            let mut health = self.health(index);
+            if healthy {
+                tracing::info!("Proxy {} passed its health check, returning it to the pool", proxy.url);
+                *health = ProxyHealth::default();
+            } else {
+                tracing::warn!("Proxy {} failed its health check", proxy.url);
+                health.quarantined_until = Some(Instant::now() + self.quarantine);
+            }
+        }
+    }
+
+    /// Runs `recheck` periodically until the pool is dropped.
+    pub fn spawn_health_checks(pool: &Arc<Self>) {
+        let Ok(handle) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+        let interval = (pool.quarantine / 4).max(Duration::from_secs(1));
+        let pool: Weak<Self> = Arc::downgrade(pool);
+        handle.spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                let Some(pool) = pool.upgrade() else {
+                    break;
+                };
+                pool.recheck().await;
+            }
+        });
+    }
+}
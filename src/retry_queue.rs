@@ -0,0 +1,129 @@
+use crate::config::RetryConfig;
+use crate::error::CrawlifyError;
+use crate::storage::models::{DeadLetter, FrontierEntry};
+use crate::storage::Storage;
+use anyhow::Result;
+use chrono::Utc;
+use std::time::Duration;
+use url::Url;
+
+/// What happened to a failed URL.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RetryOutcome {
+    /// Queued again; holds the attempt count so far.
+    Scheduled(u32),
+    DeadLettered,
+}
+
+/// Failed fetches kept in the `frontier` table so they are retried by later crawls,
+/// with a delay that grows with every attempt.
+#[derive(Clone)]
+pub struct RetryQueue {
+    db: Storage,
+    max_tries: u32,
+    base_delay: Duration,
+    max_delay: Duration,
+}
+
+impl RetryQueue {
+    pub fn new(config: &RetryConfig, db: Storage) -> Self {
+        RetryQueue {
+            db,
+            max_tries: config.max_tries.max(1),
+            base_delay: config.base_delay,
+            max_delay: config.max_delay,
+        }
+    }
+
+    /// Delay before retry number `tries`, starting at 1.
+    fn delay_for(&self, tries: u32) -> Duration {
+        let factor = 2u32.saturating_pow(tries.saturating_sub(1));
+        self.base_delay.saturating_mul(factor).min(self.max_delay)
+    }
+
+    /// Records a failed fetch. Errors that retrying cannot fix go straight to the
+    /// dead-letter list; a host given up on by the circuit breaker may be back by the
+    /// next crawl, so its URLs are retried. Dead-lettered URLs stay there until
+    /// `crawlify retry` puts them back.
+    pub async fn failed(&self, scope_id: i64, url: &Url, error: &CrawlifyError) -> Result<RetryOutcome> {
+        let conn = self.db.pool.get().await?;
+        if DeadLetter::find_by_url(&conn, url.as_str())?.is_some() {
+            return Ok(RetryOutcome::DeadLettered);
+        }
+        let tries = FrontierEntry::find_by_url(&conn, url.as_str())?.map_or(0, |entry| entry.tries) + 1;
+        let message = error.to_string();
+        let permanent = !error.is_retryable()
+            && !matches!(error, CrawlifyError::HostDead(_) | CrawlifyError::CircuitOpen { .. });
+
+        if permanent || tries >= self.max_tries {
+            DeadLetter::create(&conn, scope_id, url.as_str(), tries, &message)?;
+            tracing::warn!("Giving up on {} after {} attempts: {}", url, tries, message);
+            return Ok(RetryOutcome::DeadLettered);
+        }
+
+        let delay = self.delay_for(tries);
+        let next_allowed_at = Utc::now() + chrono::Duration::from_std(delay)?;
+        let host = url.host_str().unwrap_or_default();
+        FrontierEntry::record_failure(&conn, scope_id, url.as_str(), host, &message, tries, next_allowed_at)?;
+        tracing::info!("Will retry {} in {:?} (attempt {})", url, delay, tries);
+        Ok(RetryOutcome::Scheduled(tries))
+    }
+
+    /// Forgets a URL once it has been fetched successfully.
+    pub async fn succeeded(&self, url: &Url) -> Result<()> {
+        let conn = self.db.pool.get().await?;
+        FrontierEntry::remove(&conn, url.as_str())?;
+        Ok(())
+    }
+
+    /// URLs of the given scopes whose retry delay has passed.
+    pub async fn due(&self, scope_ids: &[i64]) -> Result<Vec<Url>> {
+        let conn = self.db.pool.get().await?;
+        Ok(FrontierEntry::due(&conn, scope_ids, Utc::now())?
+            .into_iter()
+            .filter_map(|entry| Url::parse(&entry.url).ok())
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::models::Scope;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn dead_lettered_urls_are_not_queued_again() {
+        let path = std::env::temp_dir().join(format!("crawlify-retry-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let db = Storage::new(path.to_str().unwrap()).await.unwrap();
+        db.init().await.unwrap();
+        let config = RetryConfig {
+            max_tries: 2,
+            ..Default::default()
+        };
+        let queue = RetryQueue::new(&config, db.clone());
+        let scope = Scope::create(&db.pool.get().await.unwrap(), "https://example.com/*").unwrap();
+        let url = Url::parse("https://example.com/flaky").unwrap();
+        let error = CrawlifyError::ServerError(503, url.to_string());
+
+        assert_eq!(queue.failed(scope.id, &url, &error).await.unwrap(), RetryOutcome::Scheduled(1));
+        assert_eq!(queue.failed(scope.id, &url, &error).await.unwrap(), RetryOutcome::DeadLettered);
+        assert_eq!(queue.failed(scope.id, &url, &error).await.unwrap(), RetryOutcome::DeadLettered);
+
+        let conn = db.pool.get().await.unwrap();
+        assert!(FrontierEntry::find_by_url(&conn, url.as_str()).unwrap().is_none());
+        assert_eq!(DeadLetter::find_by_url(&conn, url.as_str()).unwrap().unwrap().tries, 2);
+        drop(conn);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn only_transient_errors_are_retried() {
+        assert!(CrawlifyError::ServerError(503, "https://example.com/".to_string()).is_retryable());
+        assert!(CrawlifyError::NoHealthyProxy.is_retryable());
+        assert!(!CrawlifyError::Url(url::ParseError::EmptyHost).is_retryable());
+        assert!(!CrawlifyError::Database(rusqlite::Error::QueryReturnedNoRows).is_retryable());
+        assert!(!CrawlifyError::Anyhow(anyhow::anyhow!("unexpected")).is_retryable());
+        assert!(!CrawlifyError::SsrfBlocked("blocked".to_string()).is_retryable());
+    }
+}
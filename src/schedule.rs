@@ -0,0 +1,110 @@
+//! Recrawl schedules for `crawlify daemon`: an interval such as `6h`, or a cron
+//! expression such as `0 3 * * *` (minute first; a leading seconds field is optional).
+
+use chrono::{DateTime, Utc};
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+#[derive(Debug, Clone)]
+pub enum Schedule {
+    Interval(Duration),
+    Cron(Box<cron::Schedule>),
+}
+
+impl Schedule {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let value = value.trim();
+        if let Ok(interval) = humantime::parse_duration(value) {
+            if interval.is_zero() {
+                return Err("schedule interval must be longer than zero".to_string());
+            }
+            return Ok(Schedule::Interval(interval));
+        }
+        let expression = match value.split_whitespace().count() {
+            5 => format!("0 {}", value),
+            6 | 7 => value.to_string(),
+            _ => {
+                return Err(format!(
+                    "Invalid schedule '{}': expected an interval such as 6h or a cron expression such as '0 3 * * *'",
+                    value
+                ))
+            }
+        };
+        cron::Schedule::from_str(&expression)
+            .map(|schedule| Schedule::Cron(Box::new(schedule)))
+            .map_err(|e| {
+                // Parse errors point at the failing field on a line of their own.
+                let reason = e.to_string().lines().last().unwrap_or_default().to_string();
+                format!("Invalid schedule '{}': {}", value, reason)
+            })
+    }
+
+    /// When a scope last crawled at `last` is due again.
+    pub fn next_due(&self, last: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self {
+            Schedule::Interval(interval) => Some(last + chrono::Duration::from_std(*interval).ok()?),
+            Schedule::Cron(schedule) => schedule.after(&last).next(),
+        }
+    }
+}
+
+impl fmt::Display for Schedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Schedule::Interval(interval) => write!(f, "every {}", humantime::format_duration(*interval)),
+            Schedule::Cron(schedule) => write!(f, "cron {}", schedule),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(y: i32, mo: u32, d: u32, h: u32, mi: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(y, mo, d, h, mi, 0).unwrap()
+    }
+
+    #[test]
+    fn parses_intervals() {
+        match Schedule::parse(" 6h ") {
+            Ok(Schedule::Interval(interval)) => assert_eq!(interval, Duration::from_secs(6 * 3600)),
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(Schedule::parse("0s").is_err());
+    }
+
+    #[test]
+    fn parses_cron_with_and_without_seconds() {
+        assert!(matches!(Schedule::parse("0 3 * * *"), Ok(Schedule::Cron(_))));
+        assert!(matches!(Schedule::parse("30 0 3 * * *"), Ok(Schedule::Cron(_))));
+        assert!(matches!(Schedule::parse("0 0 3 * * * 2030"), Ok(Schedule::Cron(_))));
+    }
+
+    #[test]
+    fn rejects_invalid_schedules() {
+        for value in ["", "soon", "* * *", "61 3 * * *", "0 25 * * *"] {
+            let error = Schedule::parse(value).unwrap_err();
+            assert!(error.starts_with("Invalid schedule"), "{}", error);
+            assert!(!error.contains('\n'), "{:?} should be one line", error);
+        }
+    }
+
+    #[test]
+    fn interval_is_due_after_last_run() {
+        let schedule = Schedule::parse("90m").unwrap();
+        assert_eq!(schedule.next_due(at(2026, 1, 1, 10, 0)), Some(at(2026, 1, 1, 11, 30)));
+    }
+
+    #[test]
+    fn cron_is_due_at_next_match_after_last_run() {
+        let schedule = Schedule::parse("0 3 * * *").unwrap();
+        assert_eq!(schedule.next_due(at(2026, 1, 1, 2, 59)), Some(at(2026, 1, 1, 3, 0)));
+        assert_eq!(schedule.next_due(at(2026, 1, 1, 3, 0)), Some(at(2026, 1, 2, 3, 0)));
+        let weekdays = Schedule::parse("0 3 * * Mon-Fri").unwrap();
+        // 2026-01-03 is a Saturday.
+        assert_eq!(weekdays.next_due(at(2026, 1, 3, 12, 0)), Some(at(2026, 1, 5, 3, 0)));
+    }
+}
@@ -0,0 +1,179 @@
+//! Effective settings of a scope. Each setting is taken from the first of: the scope's
+//! settings stored with `crawlify set`, its `scope_overrides` entry in the config file,
+//! the top-level config, or the built-in default.
+
+use crate::budget;
+use crate::config::{Config, NlpConfig};
+use crate::schedule::Schedule;
+use crate::storage::models::{CrawlMethod, Scope};
+use std::collections::HashMap;
+use std::fmt;
+use std::time::Duration;
+
+/// Settings that `crawlify set` stores per scope.
+pub const SETTINGS: &[&str] = &["keywords", "request_delay", "concurrency", "export_path", "pdf_dir", "schedule"];
+
+const DEFAULT_PDF_DIR: &str = "crawled_pdfs";
+
+/// Where an effective setting comes from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Source {
+    Default,
+    Config,
+    Override,
+    Scope,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Source::Default => "default",
+            Source::Config => "config",
+            Source::Override => "override",
+            Source::Scope => "scope",
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Setting<T> {
+    pub value: T,
+    pub source: Source,
+}
+
+fn layered<T>(stored: Option<T>, overridden: Option<T>, base: Setting<T>) -> Setting<T> {
+    match (stored, overridden) {
+        (Some(value), _) => Setting { value, source: Source::Scope },
+        (None, Some(value)) => Setting { value, source: Source::Override },
+        (None, None) => base,
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ScopeConfig {
+    /// Keyword filtering runs when `nlp.enabled` is set or the scope uses the NLP method.
+    pub nlp_enabled: bool,
+    pub keywords: Setting<Vec<String>>,
+    /// `None` leaves the delay to the adaptive per-host politeness.
+    pub request_delay: Setting<Option<Duration>>,
+    pub concurrency: Setting<usize>,
+    pub export_path: Setting<String>,
+    pub pdf_dir: Setting<String>,
+    /// When `crawlify daemon` recrawls the scope; `None` leaves it to manual crawls.
+    pub schedule: Setting<Option<Schedule>>,
+}
+
+impl ScopeConfig {
+    /// Merges `config` with the settings stored for `scope`.
+    pub fn resolve(config: &Config, scope: &Scope, stored: &HashMap<String, String>) -> Result<Self, String> {
+        let overrides = config.scope_overrides_for(&scope.pattern).cloned().unwrap_or_default();
+        let stored = |key: &str| stored.get(key).map(String::as_str);
+        Ok(ScopeConfig {
+            nlp_enabled: config.nlp.enabled || scope.method == CrawlMethod::NLP,
+            keywords: layered(
+                stored("keywords").map(parse_keywords).transpose()?,
+                overrides.keywords,
+                Setting { value: config.nlp.keywords.clone(), source: Source::Config },
+            ),
+            request_delay: layered(
+                stored("request_delay").map(parse_delay).transpose()?.map(Some),
+                overrides.request_delay.map(Some),
+                Setting { value: None, source: Source::Default },
+            ),
+            concurrency: layered(
+                stored("concurrency").map(parse_concurrency).transpose()?,
+                overrides.concurrency,
+                Setting { value: config.get_concurrency().0, source: Source::Default },
+            ),
+            export_path: layered(
+                stored("export_path").map(str::to_string),
+                overrides.export_path,
+                Setting { value: config.export_path.clone(), source: Source::Config },
+            ),
+            pdf_dir: layered(
+                stored("pdf_dir").map(str::to_string),
+                overrides.pdf_dir,
+                Setting { value: DEFAULT_PDF_DIR.to_string(), source: Source::Default },
+            ),
+            schedule: layered(
+                stored("schedule").map(Schedule::parse).transpose()?.map(Some),
+                overrides.schedule.as_deref().map(Schedule::parse).transpose()?.map(Some),
+                Setting { value: None, source: Source::Default },
+            ),
+        })
+    }
+
+    pub fn nlp(&self) -> NlpConfig {
+        NlpConfig {
+            enabled: self.nlp_enabled,
+            keywords: self.keywords.value.clone(),
+        }
+    }
+
+    /// One `name: value (source)` line per setting, followed by the scope's budget.
+    pub fn describe(&self, scope: &Scope) -> Vec<String> {
+        let keywords = if self.keywords.value.is_empty() {
+            "-".to_string()
+        } else {
+            self.keywords.value.join(", ")
+        };
+        let request_delay = match self.request_delay.value {
+            Some(delay) => humantime::format_duration(delay).to_string(),
+            None => "adaptive".to_string(),
+        };
+        let schedule = match &self.schedule.value {
+            Some(schedule) => schedule.to_string(),
+            None => "manual".to_string(),
+        };
+        let budget = match budget::describe(&scope.budget) {
+            limits if limits.is_empty() => "unlimited".to_string(),
+            limits => limits,
+        };
+        vec![
+            format!("nlp: {}", if self.nlp_enabled { "on" } else { "off" }),
+            format!("keywords: {} ({})", keywords, self.keywords.source),
+            format!("request_delay: {} ({})", request_delay, self.request_delay.source),
+            format!("concurrency: {} ({})", self.concurrency.value, self.concurrency.source),
+            format!("export_path: {} ({})", self.export_path.value, self.export_path.source),
+            format!("pdf_dir: {} ({})", self.pdf_dir.value, self.pdf_dir.source),
+            format!("schedule: {} ({})", schedule, self.schedule.source),
+            format!("budget: {}", budget),
+        ]
+    }
+}
+
+fn parse_keywords(value: &str) -> Result<Vec<String>, String> {
+    let keywords: Vec<String> = value
+        .split(',')
+        .map(|k| k.trim().to_string())
+        .filter(|k| !k.is_empty())
+        .collect();
+    if keywords.is_empty() {
+        return Err("keywords must list at least one keyword".to_string());
+    }
+    Ok(keywords)
+}
+
+fn parse_delay(value: &str) -> Result<Duration, String> {
+    humantime::parse_duration(value.trim()).map_err(|e| format!("Invalid request_delay '{}': {}", value, e))
+}
+
+fn parse_concurrency(value: &str) -> Result<usize, String> {
+    match value.trim().parse::<usize>() {
+        Ok(n) if n > 0 => Ok(n),
+        _ => Err(format!("Invalid concurrency '{}': expected a positive number", value)),
+    }
+}
+
+/// Checks `value` for the setting `key` and returns it in the form it is stored.
+pub fn normalize(key: &str, value: &str) -> Result<String, String> {
+    match key {
+        "keywords" => parse_keywords(value).map(|keywords| keywords.join(", ")),
+        "request_delay" => parse_delay(value).map(|delay| humantime::format_duration(delay).to_string()),
+        "concurrency" => parse_concurrency(value).map(|n| n.to_string()),
+        "schedule" => Schedule::parse(value).map(|_| value.trim().to_string()),
+        "export_path" | "pdf_dir" if value.trim().is_empty() => Err(format!("{} cannot be empty", key)),
+        "export_path" | "pdf_dir" => Ok(value.trim().to_string()),
+        _ => Err(format!("Unknown setting '{}'", key)),
+    }
+}
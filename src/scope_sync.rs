@@ -0,0 +1,213 @@
+//! Reconciles the `scopes:` section of the config file with the `scopes` table.
+//! Declared scopes are created or updated; active scopes missing from the file are
+//! deactivated rather than removed, so their pages and history stay in place.
+
+use crate::budget;
+use crate::config::{BudgetConfig, Config, ScopeDefinition};
+use crate::error::CrawlifyError;
+use crate::scope_config;
+use crate::storage::models::{CrawlMethod, Scope};
+use rusqlite::Connection;
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::time::Duration;
+
+const METHODS: &[&str] = &["DEFAULT", "NLP", "HEADERS", "CHANGED"];
+
+/// A scope as the config file declares it, with settings in their stored form.
+#[derive(Debug, Clone)]
+pub struct DesiredScope {
+    pub pattern: String,
+    pub method: CrawlMethod,
+    pub budget: BudgetConfig,
+    pub settings: BTreeMap<String, String>,
+}
+
+impl DesiredScope {
+    fn from_definition(config: &Config, definition: &ScopeDefinition) -> Result<Self, String> {
+        let method = match &definition.method {
+            Some(method) if METHODS.contains(&method.to_uppercase().as_str()) => {
+                CrawlMethod::from(method.to_uppercase().as_str())
+            }
+            Some(method) => return Err(format!("unknown method '{}', expected one of {}", method, METHODS.join(", "))),
+            None if config.nlp.enabled => CrawlMethod::NLP,
+            None => CrawlMethod::DEFAULT,
+        };
+        let values = [
+            ("keywords", definition.keywords.as_ref().map(|keywords| keywords.join(", "))),
+            ("request_delay", definition.request_delay.map(|delay| humantime::format_duration(delay).to_string())),
+            ("concurrency", definition.concurrency.map(|n| n.to_string())),
+            ("export_path", definition.export_path.clone()),
+            ("pdf_dir", definition.pdf_dir.clone()),
+            ("schedule", definition.schedule.clone()),
+        ];
+        let mut settings = BTreeMap::new();
+        for (key, value) in values {
+            if let Some(value) = value {
+                settings.insert(key.to_string(), scope_config::normalize(key, &value)?);
+            }
+        }
+        let mut budget = definition.budget.clone();
+        // The database keeps durations in whole seconds.
+        budget.max_duration = budget.max_duration.map(|max| Duration::from_secs(max.as_secs()));
+        Ok(DesiredScope {
+            pattern: definition.pattern.clone(),
+            method,
+            budget,
+            settings,
+        })
+    }
+}
+
+/// One property that differs between the database and the config file.
+#[derive(Debug, Clone)]
+pub struct FieldChange {
+    pub name: String,
+    pub from: Option<String>,
+    pub to: Option<String>,
+}
+
+impl fmt::Display for FieldChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = |v: &Option<String>| v.clone().unwrap_or_else(|| "-".to_string());
+        write!(f, "{}: {} -> {}", self.name, value(&self.from), value(&self.to))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum ScopeChange {
+    Create { desired: DesiredScope, fields: Vec<FieldChange> },
+    Update { id: i64, desired: DesiredScope, fields: Vec<FieldChange> },
+    Deactivate { id: i64, pattern: String },
+}
+
+impl fmt::Display for ScopeChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScopeChange::Create { desired, fields } => {
+                write!(f, "+ create {}", desired.pattern)?;
+                for field in fields {
+                    write!(f, "\n    {}", field)?;
+                }
+                Ok(())
+            }
+            ScopeChange::Update { id, desired, fields } => {
+                write!(f, "~ update {} (ID {})", desired.pattern, id)?;
+                for field in fields {
+                    write!(f, "\n    {}", field)?;
+                }
+                Ok(())
+            }
+            ScopeChange::Deactivate { id, pattern } => write!(f, "- deactivate {} (ID {})", pattern, id),
+        }
+    }
+}
+
+/// Properties of `desired` that differ from `current`, or from an empty scope.
+fn diff(current: Option<(&Scope, &BTreeMap<String, String>)>, desired: &DesiredScope) -> Vec<FieldChange> {
+    let mut fields = Vec::new();
+    let mut push = |name: &str, from: Option<String>, to: Option<String>| {
+        if from != to {
+            fields.push(FieldChange { name: name.to_string(), from, to });
+        }
+    };
+    let (scope, settings) = match current {
+        Some((scope, settings)) => (Some(scope), settings.clone()),
+        None => (None, BTreeMap::new()),
+    };
+    if let Some(scope) = scope {
+        if !scope.is_active {
+            push("active", Some("no".to_string()), Some("yes".to_string()));
+        }
+    }
+    push(
+        "method",
+        scope.map(|s| s.method.as_str().to_string()),
+        Some(desired.method.as_str().to_string()),
+    );
+    for key in scope_config::SETTINGS {
+        push(key, settings.get(*key).cloned(), desired.settings.get(*key).cloned());
+    }
+    let no_budget = BudgetConfig::default();
+    let current_budget = scope.map(|s| &s.budget).unwrap_or(&no_budget);
+    for limit in budget::LIMITS {
+        push(
+            limit,
+            budget::limit_value(current_budget, limit),
+            budget::limit_value(&desired.budget, limit),
+        );
+    }
+    fields
+}
+
+/// Changes that bring the `scopes` table in line with `config.scopes`.
+pub fn plan(conn: &Connection, config: &Config) -> Result<Vec<ScopeChange>, CrawlifyError> {
+    let mut declared = HashSet::new();
+    let mut desired_scopes = Vec::new();
+    for definition in &config.scopes {
+        if !declared.insert(definition.pattern.as_str()) {
+            return Err(CrawlifyError::InvalidScope(definition.pattern.clone(), "declared twice".to_string()));
+        }
+        let desired = DesiredScope::from_definition(config, definition)
+            .map_err(|e| CrawlifyError::InvalidScope(definition.pattern.clone(), e))?;
+        desired_scopes.push(desired);
+    }
+
+    let mut existing = Scope::get_all(conn)?;
+    existing.sort_by_key(|scope| scope.id);
+    let mut changes = Vec::new();
+    for desired in desired_scopes {
+        match existing.iter().find(|scope| scope.pattern == desired.pattern) {
+            Some(scope) => {
+                let settings: BTreeMap<String, String> = Scope::settings(conn, scope.id)?
+                    .into_iter()
+                    .filter(|(key, _)| scope_config::SETTINGS.contains(&key.as_str()))
+                    .collect();
+                let fields = diff(Some((scope, &settings)), &desired);
+                if !fields.is_empty() {
+                    changes.push(ScopeChange::Update { id: scope.id, desired, fields });
+                }
+            }
+            None => {
+                let fields = diff(None, &desired);
+                changes.push(ScopeChange::Create { desired, fields });
+            }
+        }
+    }
+    for scope in existing {
+        if scope.is_active && !declared.contains(scope.pattern.as_str()) {
+            changes.push(ScopeChange::Deactivate { id: scope.id, pattern: scope.pattern });
+        }
+    }
+    Ok(changes)
+}
+
+fn write_scope(conn: &Connection, id: i64, desired: &DesiredScope) -> Result<(), CrawlifyError> {
+    let scope = Scope::find_by_id(conn, id)?;
+    Scope::set_active(conn, id, true)?;
+    Scope::update_method(conn, id, desired.method.clone(), scope.keywords)?;
+    Scope::set_budget(conn, id, &desired.budget)?;
+    for key in scope_config::SETTINGS {
+        Scope::set_setting(conn, id, key, desired.settings.get(*key).map(String::as_str))?;
+    }
+    Ok(())
+}
+
+/// Applies `changes` in a single transaction.
+pub fn apply(conn: &Connection, changes: &[ScopeChange]) -> Result<(), CrawlifyError> {
+    let tx = conn.unchecked_transaction()?;
+    for change in changes {
+        match change {
+            ScopeChange::Create { desired, .. } => {
+                let scope = Scope::create(&tx, &desired.pattern)?;
+                write_scope(&tx, scope.id, desired)?;
+            }
+            ScopeChange::Update { id, desired, .. } => write_scope(&tx, *id, desired)?,
+            ScopeChange::Deactivate { id, .. } => {
+                Scope::set_active(&tx, *id, false)?;
+            }
+        }
+    }
+    tx.commit()?;
+    Ok(())
+}
@@ -0,0 +1,36 @@
+use tokio_util::sync::CancellationToken;
+
+/// Listens for Ctrl-C and SIGTERM. The first signal cancels the returned token so the
+/// crawl can wind down; a second one exits the process immediately.
+pub fn listen() -> CancellationToken {
+    let token = CancellationToken::new();
+    let cancel = token.clone();
+    tokio::spawn(async move {
+        if let Err(e) = signal().await {
+            tracing::error!("Failed to listen for shutdown signals: {}", e);
+            return;
+        }
+        tracing::warn!("Shutting down after in-flight requests finish; press Ctrl-C again to exit immediately");
+        cancel.cancel();
+        if signal().await.is_ok() {
+            tracing::warn!("Exiting immediately");
+            std::process::exit(130);
+        }
+    });
+    token
+}
+
+#[cfg(unix)]
+async fn signal() -> std::io::Result<()> {
+    use tokio::signal::unix::{signal, SignalKind};
+    let mut terminate = signal(SignalKind::terminate())?;
+    tokio::select! {
+        result = tokio::signal::ctrl_c() => result,
+        _ = terminate.recv() => Ok(()),
+    }
+}
+
+#[cfg(not(unix))]
+async fn signal() -> std::io::Result<()> {
+    tokio::signal::ctrl_c().await
+}
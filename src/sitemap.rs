@@ -30,9 +30,9 @@ impl<'a> SitemapFetcher<'a> {
 
         // Note: A proper robots.txt parser should be used here.
         // For now, we'll just check for sitemap directives.
-        if let Ok(resp) = self.http_client.get_client().get(robots_txt_url).send().await {
-            if resp.status().is_success() {
-                let text = resp.text().await?;
+        if let Ok(resp) = self.http_client.get(&robots_txt_url).await {
+            if resp.status.is_success() {
+                let text = resp.text();
                 for line in text.lines() {
                     if line.to_lowercase().starts_with("sitemap:") {
                         if let Ok(sitemap_url) = Url::parse(line.split_at(8).1.trim()) {
@@ -48,11 +48,9 @@ impl<'a> SitemapFetcher<'a> {
             let sitemap_xml_url = url.join("/sitemap.xml")?;
             if self
                 .http_client
-                .get_client()
-                .head(sitemap_xml_url.clone())
-                .send()
+                .head(&sitemap_xml_url)
                 .await?
-                .status()
+                .status
                 .is_success()
             {
                 sitemap_urls.push(sitemap_xml_url);
@@ -63,7 +61,7 @@ impl<'a> SitemapFetcher<'a> {
     }
 
     pub async fn parse_and_stage_sitemap(&self, url: &Url) -> Result<()> {
-        let text = self.http_client.get_client().get(url.as_str()).send().await?.text().await?;
+        let text = self.http_client.get(url).await?.text();
         let mut reader = Reader::from_str(&text);
         reader.config_mut().trim_text(true);
         let mut in_loc = false;
@@ -0,0 +1,220 @@
+use crate::config::SsrfConfig;
+use anyhow::{anyhow, Result};
+use ipnet::IpNet;
+use std::collections::HashSet;
+use std::net::IpAddr;
+use url::{Host, Url};
+
+/// Private, loopback, link-local, shared, documentation, benchmarking, multicast and
+/// reserved ranges, plus the IPv6 transition ranges (6to4, Teredo, local-use NAT64) that
+/// can tunnel to any IPv4 address. IPv4-mapped and well-known NAT64 IPv6 addresses are
+/// checked as their IPv4 address.
+const BLOCKED_RANGES: &[&str] = &[
+    "0.0.0.0/8",
+    "10.0.0.0/8",
+    "100.64.0.0/10",
+    "127.0.0.0/8",
+    "169.254.0.0/16",
+    "172.16.0.0/12",
+    "192.0.0.0/24",
+    "192.0.2.0/24",
+    "192.88.99.0/24",
+    "192.168.0.0/16",
+    "198.18.0.0/15",
+    "198.51.100.0/24",
+    "203.0.113.0/24",
+    "224.0.0.0/4",
+    "240.0.0.0/4",
+    "::/128",
+    "::1/128",
+    "64:ff9b:1::/48",
+    "100::/64",
+    "2001::/32",
+    "2001:db8::/32",
+    "2002::/16",
+    "fc00::/7",
+    "fe80::/10",
+    "ff00::/8",
+];
+
+#[derive(Debug, thiserror::Error)]
+#[error("blocked request to {host} ({ip}): private or special-purpose address")]
+pub struct SsrfBlocked {
+    pub host: String,
+    pub ip: IpAddr,
+}
+
+/// Refuses destinations in private and special-purpose ranges unless the host or
+/// network is allowlisted. Checked on resolved addresses, so redirects and DNS
+/// rebinding cannot route around it.
+pub struct SsrfGuard {
+    enabled: bool,
+    blocked: Vec<IpNet>,
+    allow_hosts: HashSet<String>,
+    allow_nets: Vec<IpNet>,
+}
+
+fn unmap(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V6(v6) => {
+            if let Some(v4) = v6.to_ipv4_mapped() {
+                return IpAddr::V4(v4);
+            }
+            let segments = v6.segments();
+            // NAT64 well-known prefix 64:ff9b::/96
+            if segments[..6] == [0x64, 0xff9b, 0, 0, 0, 0] {
+                let octets = v6.octets();
+                return IpAddr::V4([octets[12], octets[13], octets[14], octets[15]].into());
+            }
+            ip
+        }
+        IpAddr::V4(_) => ip,
+    }
+}
+
+impl SsrfGuard {
+    /// `trusted_hosts` are always allowed, e.g. the configured proxies.
+    pub fn new<'a>(config: &SsrfConfig, trusted_hosts: impl IntoIterator<Item = &'a str>) -> Result<Self> {
+        let mut allow_hosts: HashSet<String> = trusted_hosts.into_iter().map(|h| h.to_ascii_lowercase()).collect();
+        let mut allow_nets = Vec::new();
+        for entry in &config.allow {
+            match entry.parse::<IpNet>() {
+                Ok(net) => allow_nets.push(net),
+                Err(_) => match entry.parse::<IpAddr>() {
+                    Ok(ip) => allow_nets.push(IpNet::from(ip)),
+                    Err(_) => {
+                        allow_hosts.insert(entry.to_ascii_lowercase());
+                    }
+                },
+            }
+        }
+        let blocked = BLOCKED_RANGES
+            .iter()
+            .map(|range| range.parse().map_err(|e| anyhow!("invalid range {}: {}", range, e)))
+            .collect::<Result<Vec<IpNet>>>()?;
+
+        Ok(SsrfGuard {
+            enabled: config.enabled,
+            blocked,
+            allow_hosts,
+            allow_nets,
+        })
+    }
+
+    fn is_special(&self, ip: IpAddr) -> bool {
+        let ip = unmap(ip);
+        self.blocked.iter().any(|net| net.contains(&ip))
+    }
+
+    /// Whether requests to `host` need their resolved addresses checked, i.e. the guard is
+    /// on and the host is not allowlisted.
+    pub fn checks_host(&self, host: &str) -> bool {
+        self.enabled && !self.allow_hosts.contains(&host.to_ascii_lowercase())
+    }
+
+    pub fn is_allowed(&self, host: &str, ip: IpAddr) -> bool {
+        !self.enabled
+            || !self.is_special(ip)
+            || self.allow_hosts.contains(&host.to_ascii_lowercase())
+            || self.allow_nets.iter().any(|net| net.contains(&unmap(ip)))
+    }
+
+    pub fn check(&self, host: &str, ip: IpAddr) -> std::result::Result<(), SsrfBlocked> {
+        if self.is_allowed(host, ip) {
+            return Ok(());
+        }
+        let blocked = SsrfBlocked {
+            host: host.to_string(),
+            ip,
+        };
+        tracing::warn!("{}", blocked);
+        Err(blocked)
+    }
+
+    /// Checks URLs whose host is an IP literal, which never reach the resolver.
+    pub fn check_url(&self, url: &Url) -> std::result::Result<(), SsrfBlocked> {
+        match url.host() {
+            Some(Host::Ipv4(ip)) => self.check(&ip.to_string(), IpAddr::V4(ip)),
+            Some(Host::Ipv6(ip)) => self.check(&ip.to_string(), IpAddr::V6(ip)),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn guard(allow: &[&str]) -> SsrfGuard {
+        let config = SsrfConfig {
+            enabled: true,
+            allow: allow.iter().map(|entry| entry.to_string()).collect(),
+        };
+        SsrfGuard::new(&config, ["proxy.internal"]).unwrap()
+    }
+
+    fn allowed(guard: &SsrfGuard, ip: &str) -> bool {
+        guard.is_allowed("example.com", ip.parse().unwrap())
+    }
+
+    #[test]
+    fn blocks_special_ipv4_ranges() {
+        let guard = guard(&[]);
+        for ip in ["127.0.0.1", "10.1.2.3", "172.16.0.1", "192.168.1.1", "169.254.169.254", "100.64.0.1", "0.0.0.0"] {
+            assert!(!allowed(&guard, ip), "{} should be blocked", ip);
+        }
+        for ip in ["93.184.216.34", "8.8.8.8", "172.32.0.1"] {
+            assert!(allowed(&guard, ip), "{} should be allowed", ip);
+        }
+    }
+
+    #[test]
+    fn blocks_special_ipv6_ranges() {
+        let guard = guard(&[]);
+        for ip in ["::1", "::", "fe80::1", "fd00::1", "ff02::1", "2001:db8::1", "2002:a9fe:a9fe::1", "2001:0:4136:e378::1", "64:ff9b:1::a9fe:a9fe"] {
+            assert!(!allowed(&guard, ip), "{} should be blocked", ip);
+        }
+        assert!(allowed(&guard, "2606:4700:4700::1111"));
+    }
+
+    #[test]
+    fn checks_mapped_and_nat64_addresses_as_ipv4() {
+        let guard = guard(&[]);
+        assert!(!allowed(&guard, "::ffff:127.0.0.1"));
+        assert!(!allowed(&guard, "::ffff:169.254.169.254"));
+        assert!(allowed(&guard, "::ffff:93.184.216.34"));
+        assert!(!allowed(&guard, "64:ff9b::a9fe:a9fe"));
+        assert!(!allowed(&guard, "64:ff9b::10.0.0.1"));
+        assert!(allowed(&guard, "64:ff9b::93.184.216.34"));
+    }
+
+    #[test]
+    fn honours_allowlist_and_trusted_hosts() {
+        let guard = guard(&["10.0.0.0/8", "intranet.local", "192.168.1.5"]);
+        assert!(allowed(&guard, "10.9.9.9"));
+        assert!(allowed(&guard, "::ffff:10.9.9.9"));
+        assert!(allowed(&guard, "192.168.1.5"));
+        assert!(!allowed(&guard, "192.168.1.6"));
+        assert!(guard.is_allowed("Intranet.Local", "127.0.0.1".parse().unwrap()));
+        assert!(guard.is_allowed("proxy.internal", "127.0.0.1".parse().unwrap()));
+        assert!(!guard.checks_host("proxy.internal"));
+        assert!(guard.checks_host("example.com"));
+    }
+
+    #[test]
+    fn disabled_guard_allows_everything() {
+        let guard = SsrfGuard::new(&SsrfConfig { enabled: false, allow: Vec::new() }, []).unwrap();
+        assert!(guard.is_allowed("localhost", "127.0.0.1".parse().unwrap()));
+        assert!(!guard.checks_host("localhost"));
+    }
+
+    #[test]
+    fn check_url_rejects_ip_literals() {
+        let guard = guard(&[]);
+        assert!(guard.check_url(&Url::parse("http://169.254.169.254/latest/meta-data").unwrap()).is_err());
+        assert!(guard.check_url(&Url::parse("http://[::1]:8080/").unwrap()).is_err());
+        assert!(guard.check_url(&Url::parse("http://93.184.216.34/").unwrap()).is_ok());
+        // Names are checked once resolved.
+        assert!(guard.check_url(&Url::parse("http://localhost/").unwrap()).is_ok());
+    }
+}
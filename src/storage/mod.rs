@@ -2,10 +2,51 @@ use crate::error::CrawlifyError;
 use bb8::Pool;
 use bb8_rusqlite::RusqliteConnectionManager;
 use models::{Page, Scope};
+use rusqlite::Connection;
 
 pub mod models;
 pub mod connection;
 
+/// Columns added after a table was first released. `CREATE TABLE IF NOT EXISTS`
+/// leaves existing databases without them, so `init` adds any that are missing.
+const ADDED_COLUMNS: &[(&str, &str, &str)] = &[
+    ("pages", "warc_filename", "TEXT"),
+    ("pages", "warc_offset", "INTEGER"),
+    ("pages", "content_type", "TEXT"),
+    ("pages", "body_hash", "TEXT"),
+    ("pages", "redirect_chain", "TEXT"),
+    ("frontier", "last_error", "TEXT"),
+    ("pages", "run_id", "INTEGER"),
+    ("crawl_runs", "ended_at", "DATETIME"),
+    ("crawl_runs", "scopes", "TEXT"),
+    ("crawl_runs", "exit_reason", "TEXT"),
+    ("crawl_runs", "pages_fetched", "INTEGER NOT NULL DEFAULT 0"),
+    ("crawl_runs", "pages_exported", "INTEGER NOT NULL DEFAULT 0"),
+    ("crawl_runs", "pages_duplicate", "INTEGER NOT NULL DEFAULT 0"),
+    ("crawl_runs", "pages_failed", "INTEGER NOT NULL DEFAULT 0"),
+    ("crawl_runs", "budget_limit", "TEXT"),
+    ("scopes", "max_pages", "INTEGER"),
+    ("scopes", "max_exported", "INTEGER"),
+    ("scopes", "max_depth", "INTEGER"),
+    ("scopes", "max_bytes", "INTEGER"),
+    ("scopes", "max_duration", "INTEGER"),
+];
+
+fn add_missing_columns(conn: &Connection) -> Result<(), CrawlifyError> {
+    for (table, column, decl) in ADDED_COLUMNS {
+        let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+        let exists = stmt
+            .query_map(rusqlite::params![], |row| row.get::<_, String>("name"))?
+            .collect::<Result<Vec<_>, _>>()?
+            .iter()
+            .any(|name| name == column);
+        if !exists {
+            conn.execute_batch(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, decl))?;
+        }
+    }
+    Ok(())
+}
+
 #[derive(Clone)]
 pub struct Storage {
     pub pool: Pool<RusqliteConnectionManager>,
@@ -22,6 +63,7 @@ impl Storage {
         let conn = self.pool.get().await?;
         let schema = include_str!("schema.sql");
         conn.execute_batch(schema)?;
+        add_missing_columns(&conn)?;
         Ok(())
     }
 
@@ -29,7 +71,7 @@ impl Storage {
         let conn = self.pool.get().await?;
         let mut stmt = conn.prepare("SELECT * FROM scopes WHERE is_active = 1")?;
         let scopes = stmt
-            .query_map(rusqlite::params![], |row| Scope::from_row(row))?
+            .query_map(rusqlite::params![], Scope::from_row)?
             .collect::<Result<Vec<_>, _>>()?;
         Ok(scopes)
     }
@@ -38,28 +80,47 @@ impl Storage {
         let conn = self.pool.get().await?;
         let mut stmt = conn.prepare("SELECT * FROM pages")?;
         let pages = stmt
-            .query_map(rusqlite::params![], |row| Page::from_row(row))?
+            .query_map(rusqlite::params![], Page::from_row)?
             .collect::<Result<Vec<_>, _>>()?;
         Ok(pages)
     }
 
     pub async fn get_page_by_url(&self, url: &str) -> Result<Option<Page>, CrawlifyError> {
         let conn = self.pool.get().await?;
-        match conn.query_row("SELECT * FROM pages WHERE url = ?1", rusqlite::params![url], |row| Page::from_row(row)) {
+        match conn.query_row("SELECT * FROM pages WHERE url = ?1", rusqlite::params![url], Page::from_row) {
             Ok(page) => Ok(Some(page)),
             Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
             Err(e) => Err(e.into()),
         }
     }
 
-    pub async fn save_frontier_state(&self, _state: &[u8]) -> Result<(), CrawlifyError> {
-        // Placeholder implementation
+    pub async fn save_frontier_state(&self, scope_id: i64, state: &[u8]) -> Result<(), CrawlifyError> {
+        let conn = self.pool.get().await?;
+        conn.execute(
+            "INSERT INTO frontier_state (id, state, saved_at) VALUES (?1, ?2, CURRENT_TIMESTAMP)
+             ON CONFLICT(id) DO UPDATE SET state = excluded.state, saved_at = excluded.saved_at",
+            rusqlite::params![scope_id, state],
+        )?;
         Ok(())
     }
 
-    pub async fn load_frontier_state(&self) -> Result<Option<Vec<u8>>, CrawlifyError> {
-        // Placeholder implementation
-        Ok(None)
+    pub async fn load_frontier_state(&self, scope_id: i64) -> Result<Option<Vec<u8>>, CrawlifyError> {
+        let conn = self.pool.get().await?;
+        match conn.query_row(
+            "SELECT state FROM frontier_state WHERE id = ?1",
+            rusqlite::params![scope_id],
+            |row| row.get(0),
+        ) {
+            Ok(state) => Ok(Some(state)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub async fn clear_frontier_state(&self, scope_id: i64) -> Result<(), CrawlifyError> {
+        let conn = self.pool.get().await?;
+        conn.execute("DELETE FROM frontier_state WHERE id = ?1", rusqlite::params![scope_id])?;
+        Ok(())
     }
 
     pub async fn get_frontier(&self) -> Result<crate::frontier::Frontier, CrawlifyError> {
@@ -1,6 +1,8 @@
 use serde::{Serialize, Deserialize};
 use chrono::{DateTime, Utc};
 use rusqlite::{params, Connection, Result as RusqliteResult, Row};
+use std::collections::HashMap;
+use crate::config::BudgetConfig;
 use crate::error::CrawlifyError;
 
 type Result<T> = std::result::Result<T, CrawlifyError>;
@@ -58,6 +60,7 @@ pub struct Scope {
     pub keywords: Option<String>,
     pub is_active: bool,
     pub last_crawled_at: Option<DateTime<Utc>>,
+    pub budget: BudgetConfig,
     pub created_at: DateTime<Utc>,
 }
 
@@ -71,6 +74,15 @@ impl Scope {
             keywords: row.get("keywords")?,
             is_active: row.get("is_active")?,
             last_crawled_at: row.get("last_crawled_at")?,
+            budget: BudgetConfig {
+                max_pages: row.get::<_, Option<i64>>("max_pages")?.map(|v| v as u64),
+                max_exported: row.get::<_, Option<i64>>("max_exported")?.map(|v| v as u64),
+                max_depth: row.get("max_depth")?,
+                max_bytes: row.get::<_, Option<i64>>("max_bytes")?.map(|v| v as u64),
+                max_duration: row
+                    .get::<_, Option<i64>>("max_duration")?
+                    .map(|secs| std::time::Duration::from_secs(secs as u64)),
+            },
             created_at: row.get("created_at")?,
         })
     }
@@ -103,6 +115,94 @@ impl Scope {
         Ok(rows_affected)
     }
 
+    /// Returns true if `url` falls under this scope. `*` in the pattern matches any run
+    /// of characters; a pattern without wildcards matches as a prefix.
+    pub fn matches(&self, url: &str) -> bool {
+        let mut parts = self.pattern.split('*');
+        let first = parts.next().unwrap_or_default();
+        let Some(mut rest) = url.strip_prefix(first) else {
+            return false;
+        };
+        let parts: Vec<&str> = parts.collect();
+        let Some((last, middle)) = parts.split_last() else {
+            return true;
+        };
+        for part in middle {
+            match rest.find(part) {
+                Some(index) => rest = &rest[index + part.len()..],
+                None => return false,
+            }
+        }
+        rest.ends_with(last)
+    }
+
+    pub fn set_budget(conn: &Connection, id: i64, budget: &BudgetConfig) -> Result<usize> {
+        let rows_affected = conn.execute(
+            "UPDATE scopes
+             SET max_pages = ?1, max_exported = ?2, max_depth = ?3, max_bytes = ?4, max_duration = ?5
+             WHERE id = ?6",
+            params![
+                budget.max_pages.map(|v| v as i64),
+                budget.max_exported.map(|v| v as i64),
+                budget.max_depth,
+                budget.max_bytes.map(|v| v as i64),
+                budget.max_duration.map(|d| d.as_secs() as i64),
+                id,
+            ],
+        )?;
+        Ok(rows_affected)
+    }
+
+    /// Sets `last_crawled_at` of the scope with this pattern to now.
+    pub fn mark_crawled(conn: &Connection, pattern: &str) -> Result<()> {
+        conn.execute(
+            "UPDATE scopes SET last_crawled_at = ?1 WHERE pattern = ?2",
+            params![Utc::now(), pattern],
+        )?;
+        Ok(())
+    }
+
+    pub fn set_active(conn: &Connection, id: i64, active: bool) -> Result<usize> {
+        let rows_affected = conn.execute(
+            "UPDATE scopes SET is_active = ?1 WHERE id = ?2",
+            params![active, id],
+        )?;
+        Ok(rows_affected)
+    }
+
+    pub fn update_pattern(conn: &Connection, id: i64, pattern: &str) -> Result<usize> {
+        let rows_affected = conn.execute(
+            "UPDATE scopes SET pattern = ?1 WHERE id = ?2",
+            params![pattern, id],
+        )?;
+        Ok(rows_affected)
+    }
+
+    /// Settings stored for the scope in `scope_settings`, by key.
+    pub fn settings(conn: &Connection, id: i64) -> Result<HashMap<String, String>> {
+        let mut stmt = conn.prepare("SELECT key, value FROM scope_settings WHERE scope_id = ?1")?;
+        let settings = stmt
+            .query_map(params![id], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<RusqliteResult<HashMap<String, String>>>()?;
+        Ok(settings)
+    }
+
+    /// Stores a setting of the scope; `None` removes it.
+    pub fn set_setting(conn: &Connection, id: i64, key: &str, value: Option<&str>) -> Result<()> {
+        match value {
+            Some(value) => conn.execute(
+                "INSERT INTO scope_settings (scope_id, key, value) VALUES (?1, ?2, ?3)
+                 ON CONFLICT(scope_id, key) DO UPDATE SET value = excluded.value",
+                params![id, key, value],
+            )?,
+            None => conn.execute(
+                "DELETE FROM scope_settings WHERE scope_id = ?1 AND key = ?2",
+                params![id, key],
+            )?,
+        };
+        Ok(())
+    }
+
     pub fn update_method(
         conn: &Connection,
         id: i64,
@@ -122,7 +222,7 @@ impl Scope {
     // ... existing methods ...
     pub fn get_all(conn: &Connection) -> Result<Vec<Self>> {
         let mut stmt = conn.prepare("SELECT * FROM scopes")?;
-        let scopes = stmt.query_map(rusqlite::params![], |row| Scope::from_row(row))?
+        let scopes = stmt.query_map(rusqlite::params![], Scope::from_row)?
             .collect::<RusqliteResult<Vec<Self>>>()
             .map_err(|e| StorageError::QueryError(e.to_string()))?;
         Ok(scopes)
@@ -143,9 +243,40 @@ pub struct Page {
     pub meta_json: Option<String>,
     pub etag: Option<String>,
     pub last_modified: Option<String>,
+    pub warc_filename: Option<String>,
+    pub warc_offset: Option<i64>,
+    pub content_type: Option<String>,
+    pub body_hash: Option<String>,
+    pub redirect_chain: Option<String>,
+    pub run_id: Option<i64>,
     pub created_at: DateTime<Utc>,
 }
 
+/// Fields of a freshly fetched page, written by `Page::create`.
+#[derive(Debug, Clone, Default)]
+pub struct NewPage {
+    pub url: String,
+    pub canonical_url: Option<String>,
+    pub title: Option<String>,
+    pub text_hash: Option<u64>,
+    pub sim_hash: Option<u64>,
+    /// Defaults to now; set for pages taken from archives.
+    pub fetched_at: Option<DateTime<Utc>>,
+    pub status_code: Option<i32>,
+    pub content_length: Option<i64>,
+    pub meta_json: Option<String>,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    pub warc_filename: Option<String>,
+    pub warc_offset: Option<i64>,
+    pub content_type: Option<String>,
+    pub body_hash: Option<String>,
+    /// JSON array of the redirect hops followed to reach `url`.
+    pub redirect_chain: Option<String>,
+    /// The crawl run fetching the page; `None` keeps the run of an earlier fetch.
+    pub run_id: Option<i64>,
+}
+
 impl Page {
     pub fn from_row(row: &Row) -> RusqliteResult<Self> {
         Ok(Page {
@@ -161,35 +292,62 @@ impl Page {
             meta_json: row.get("meta_json")?,
             etag: row.get("etag")?,
             last_modified: row.get("last_modified")?,
+            warc_filename: row.get("warc_filename")?,
+            warc_offset: row.get("warc_offset")?,
+            content_type: row.get("content_type")?,
+            body_hash: row.get("body_hash")?,
+            redirect_chain: row.get("redirect_chain")?,
+            run_id: row.get("run_id")?,
             created_at: row.get("created_at")?,
         })
     }
 
-    pub fn create(
-        _storage: &crate::storage::Storage,
-        url: &str,
-        text_hash: Option<u64>,
-        sim_hash: Option<u64>,
-        title: Option<String>,
-        canonical_url: Option<String>,
-    ) -> Result<Self> {
-        // For now, create a placeholder page
-        // In a real implementation, this would use the storage pool
-        Ok(Page {
-            id: 1,
-            url: url.to_string(),
-            canonical_url,
-            title,
-            text_hash: text_hash.map(|h| h.to_string()),
-            sim_hash: sim_hash.map(|h| h.to_string()),
-            fetched_at: Utc::now(),
-            status_code: Some(200),
-            content_length: None,
-            meta_json: None,
-            etag: None,
-            last_modified: None,
-            created_at: Utc::now(),
-        })
+    /// Inserts the page, or updates the existing row for the same URL on recrawl.
+    pub async fn create(storage: &crate::storage::Storage, page: &NewPage) -> Result<Self> {
+        let conn = storage.pool.get().await?;
+        conn.execute(
+            "INSERT INTO pages (url, canonical_url, title, text_hash, sim_hash, fetched_at, status_code,
+                                content_length, meta_json, etag, last_modified, warc_filename, warc_offset,
+                                content_type, body_hash, redirect_chain, run_id)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17)
+             ON CONFLICT(url) DO UPDATE SET
+                canonical_url = excluded.canonical_url,
+                title = excluded.title,
+                text_hash = excluded.text_hash,
+                sim_hash = excluded.sim_hash,
+                fetched_at = excluded.fetched_at,
+                status_code = excluded.status_code,
+                content_length = excluded.content_length,
+                meta_json = excluded.meta_json,
+                etag = excluded.etag,
+                last_modified = excluded.last_modified,
+                warc_filename = excluded.warc_filename,
+                warc_offset = excluded.warc_offset,
+                content_type = excluded.content_type,
+                body_hash = excluded.body_hash,
+                redirect_chain = excluded.redirect_chain,
+                run_id = COALESCE(excluded.run_id, pages.run_id)",
+            params![
+                page.url,
+                page.canonical_url,
+                page.title,
+                page.text_hash.map(|h| h.to_string()),
+                page.sim_hash.map(|h| h.to_string()),
+                page.fetched_at.unwrap_or_else(Utc::now),
+                page.status_code,
+                page.content_length,
+                page.meta_json,
+                page.etag,
+                page.last_modified,
+                page.warc_filename,
+                page.warc_offset,
+                page.content_type,
+                page.body_hash,
+                page.redirect_chain,
+                page.run_id,
+            ],
+        )?;
+        Self::find_by_url(&conn, &page.url)
     }
 
     pub fn find_by_id(conn: &Connection, id: i64) -> Result<Self> {
@@ -206,6 +364,33 @@ impl Page {
         .map_err(|_| StorageError::NotFound)?)
     }
 
+    /// Looks `url` up as a page URL first, then as a URL that redirected to a page.
+    pub fn find_by_url_or_alias(conn: &Connection, url: &str) -> Result<Option<Self>> {
+        let queries = [
+            "SELECT * FROM pages WHERE url = ?1",
+            "SELECT pages.* FROM pages JOIN page_aliases ON page_aliases.page_id = pages.id
+             WHERE page_aliases.alias_url = ?1",
+        ];
+        for query in queries {
+            match conn.query_row(query, params![url], Self::from_row) {
+                Ok(page) => return Ok(Some(page)),
+                Err(rusqlite::Error::QueryReturnedNoRows) => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(None)
+    }
+
+    /// Records `alias_url` as redirecting to this page, replacing any earlier target.
+    pub fn add_alias(&self, conn: &Connection, alias_url: &str) -> Result<()> {
+        conn.execute(
+            "INSERT INTO page_aliases (alias_url, page_id) VALUES (?1, ?2)
+             ON CONFLICT(alias_url) DO UPDATE SET page_id = excluded.page_id",
+            params![alias_url, self.id],
+        )?;
+        Ok(())
+    }
+
     pub fn find_by_text_hash(conn: &Connection, text_hash: &str) -> Result<Vec<Self>> {
         let mut stmt = conn.prepare("SELECT * FROM pages WHERE text_hash = ?1")?;
         let pages = stmt.query_map(params![text_hash], Self::from_row)?
@@ -225,6 +410,511 @@ impl Page {
     }
 }
 
+/// A distinct text version of a page, kept so changes survive the page row being overwritten.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PageVersion {
+    pub id: i64,
+    pub page_id: i64,
+    pub text_hash: String,
+    pub title: Option<String>,
+    pub text: String,
+    pub body_hash: Option<String>,
+    pub fetched_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl PageVersion {
+    pub fn from_row(row: &Row) -> RusqliteResult<Self> {
+        Ok(PageVersion {
+            id: row.get("id")?,
+            page_id: row.get("page_id")?,
+            text_hash: row.get("text_hash")?,
+            title: row.get("title")?,
+            text: row.get("text")?,
+            body_hash: row.get("body_hash")?,
+            fetched_at: row.get("fetched_at")?,
+            created_at: row.get("created_at")?,
+        })
+    }
+
+    /// Stores the page's current text as a new version unless it is the same as the
+    /// version fetched most recently before it. Returns true if a version was added.
+    pub fn record(conn: &Connection, page: &Page, text: &str) -> Result<bool> {
+        let Some(text_hash) = &page.text_hash else {
+            return Ok(false);
+        };
+        let previous: Option<String> = conn
+            .query_row(
+                "SELECT text_hash FROM page_versions WHERE page_id = ?1 AND fetched_at <= ?2
+                 ORDER BY fetched_at DESC, id DESC LIMIT 1",
+                params![page.id, page.fetched_at],
+                |row| row.get(0),
+            )
+            .map(Some)
+            .or_else(|e| match e {
+                rusqlite::Error::QueryReturnedNoRows => Ok(None),
+                e => Err(e),
+            })?;
+        if previous.as_ref() == Some(text_hash) {
+            return Ok(false);
+        }
+        conn.execute(
+            "INSERT INTO page_versions (page_id, text_hash, title, text, body_hash, fetched_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![page.id, text_hash, page.title, text, page.body_hash, page.fetched_at],
+        )?;
+        Ok(true)
+    }
+
+    /// All versions of a page, oldest first.
+    pub fn for_page(conn: &Connection, page_id: i64) -> Result<Vec<Self>> {
+        let mut stmt = conn.prepare(
+            "SELECT * FROM page_versions WHERE page_id = ?1 ORDER BY fetched_at, id",
+        )?;
+        let versions = stmt.query_map(params![page_id], Self::from_row)?
+            .collect::<RusqliteResult<Vec<Self>>>()
+            .map_err(|e| StorageError::QueryError(e.to_string()))?;
+        Ok(versions)
+    }
+}
+
+/// Change statistics of a page, kept up to date by live fetches.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PageFreshness {
+    pub page_id: i64,
+    /// Fetches after the first one.
+    pub checks: i64,
+    /// Checks that found content different from the fetch before.
+    pub changes: i64,
+    pub first_fetched_at: DateTime<Utc>,
+    pub last_fetched_at: DateTime<Utc>,
+    pub next_visit_at: DateTime<Utc>,
+}
+
+impl PageFreshness {
+    pub fn from_row(row: &Row) -> RusqliteResult<Self> {
+        Ok(PageFreshness {
+            page_id: row.get("page_id")?,
+            checks: row.get("checks")?,
+            changes: row.get("changes")?,
+            first_fetched_at: row.get("first_fetched_at")?,
+            last_fetched_at: row.get("last_fetched_at")?,
+            next_visit_at: row.get("next_visit_at")?,
+        })
+    }
+
+    pub fn find(conn: &Connection, page_id: i64) -> Result<Option<Self>> {
+        match conn.query_row(
+            "SELECT * FROM page_freshness WHERE page_id = ?1",
+            params![page_id],
+            Self::from_row,
+        ) {
+            Ok(freshness) => Ok(Some(freshness)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn save(&self, conn: &Connection) -> Result<()> {
+        conn.execute(
+            "INSERT INTO page_freshness (page_id, checks, changes, first_fetched_at, last_fetched_at, next_visit_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+             ON CONFLICT(page_id) DO UPDATE SET
+               checks = excluded.checks,
+               changes = excluded.changes,
+               first_fetched_at = excluded.first_fetched_at,
+               last_fetched_at = excluded.last_fetched_at,
+               next_visit_at = excluded.next_visit_at",
+            params![
+                self.page_id,
+                self.checks,
+                self.changes,
+                self.first_fetched_at,
+                self.last_fetched_at,
+                self.next_visit_at,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Pages due for a revisit at `now` with their statistics, most overdue first.
+    pub fn due(conn: &Connection, now: DateTime<Utc>) -> Result<Vec<(Page, Self)>> {
+        let mut stmt = conn.prepare(
+            "SELECT p.*, f.page_id, f.checks, f.changes, f.first_fetched_at, f.last_fetched_at, f.next_visit_at
+             FROM page_freshness f JOIN pages p ON p.id = f.page_id
+             WHERE f.next_visit_at <= ?1
+             ORDER BY f.next_visit_at",
+        )?;
+        let due = stmt
+            .query_map(params![now], |row| Ok((Page::from_row(row)?, Self::from_row(row)?)))?
+            .collect::<RusqliteResult<Vec<_>>>()?;
+        Ok(due)
+    }
+
+    /// Pages fetched before change statistics were kept.
+    pub fn untracked_pages(conn: &Connection) -> Result<Vec<Page>> {
+        let mut stmt = conn.prepare(
+            "SELECT * FROM pages WHERE id NOT IN (SELECT page_id FROM page_freshness)",
+        )?;
+        let pages = stmt
+            .query_map(params![], Page::from_row)?
+            .collect::<RusqliteResult<Vec<_>>>()?;
+        Ok(pages)
+    }
+}
+
+/// One `crawlify crawl` invocation.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CrawlRun {
+    pub id: i64,
+    pub started_at: DateTime<Utc>,
+    pub ended_at: Option<DateTime<Utc>>,
+    /// JSON array of the scope patterns crawled.
+    pub scopes: Option<String>,
+    pub exit_reason: Option<String>,
+    pub pages_fetched: i64,
+    pub pages_exported: i64,
+    pub pages_duplicate: i64,
+    pub pages_failed: i64,
+    /// Run budget limit that stopped the run, if any.
+    pub budget_limit: Option<String>,
+}
+
+/// Page counts of a finished run, written by `CrawlRun::finish`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RunCounts {
+    pub fetched: u64,
+    pub exported: u64,
+    pub duplicate: u64,
+    pub failed: u64,
+}
+
+impl CrawlRun {
+    pub fn from_row(row: &Row) -> RusqliteResult<Self> {
+        Ok(CrawlRun {
+            id: row.get("id")?,
+            started_at: row.get("started_at")?,
+            ended_at: row.get("ended_at")?,
+            scopes: row.get("scopes")?,
+            exit_reason: row.get("exit_reason")?,
+            pages_fetched: row.get("pages_fetched")?,
+            pages_exported: row.get("pages_exported")?,
+            pages_duplicate: row.get("pages_duplicate")?,
+            pages_failed: row.get("pages_failed")?,
+            budget_limit: row.get("budget_limit")?,
+        })
+    }
+
+    pub fn start(conn: &Connection, scopes: &[String]) -> Result<Self> {
+        conn.execute(
+            "INSERT INTO crawl_runs (started_at, scopes) VALUES (?1, ?2)",
+            params![Utc::now(), serde_json::to_string(scopes).unwrap_or_default()],
+        )?;
+        Self::find_by_id(conn, conn.last_insert_rowid())
+    }
+
+    /// Stamps the end time, exit reason and page counts of the run.
+    pub fn finish(&self, conn: &Connection, exit_reason: &str, budget_limit: Option<&str>, counts: RunCounts) -> Result<()> {
+        conn.execute(
+            "UPDATE crawl_runs
+             SET ended_at = ?1, exit_reason = ?2, pages_fetched = ?3, pages_exported = ?4,
+                 pages_duplicate = ?5, pages_failed = ?6, budget_limit = ?8
+             WHERE id = ?7",
+            params![
+                Utc::now(),
+                exit_reason,
+                counts.fetched as i64,
+                counts.exported as i64,
+                counts.duplicate as i64,
+                counts.failed as i64,
+                self.id,
+                budget_limit,
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn find_by_id(conn: &Connection, id: i64) -> Result<Self> {
+        Ok(conn.query_row("SELECT * FROM crawl_runs WHERE id = ?1", params![id], Self::from_row)
+            .map_err(|_| StorageError::NotFound)?)
+    }
+
+    /// The most recent runs, newest first.
+    pub fn recent(conn: &Connection, limit: usize) -> Result<Vec<Self>> {
+        let mut stmt = conn.prepare("SELECT * FROM crawl_runs ORDER BY id DESC LIMIT ?1")?;
+        let runs = stmt.query_map(params![limit as i64], Self::from_row)?
+            .collect::<RusqliteResult<Vec<Self>>>()
+            .map_err(|e| StorageError::QueryError(e.to_string()))?;
+        Ok(runs)
+    }
+
+    /// Fetch errors recorded during the run.
+    pub fn error_count(&self, conn: &Connection) -> Result<i64> {
+        Ok(conn.query_row("SELECT COUNT(*) FROM fetch_errors WHERE run_id = ?1", params![self.id], |row| row.get(0))?)
+    }
+
+    pub fn duration(&self) -> Option<chrono::Duration> {
+        self.ended_at.map(|ended_at| ended_at - self.started_at)
+    }
+}
+
+/// Fields of a failure, written by `FetchError::create`.
+#[derive(Debug, Clone, Default)]
+pub struct NewFetchError {
+    pub run_id: Option<i64>,
+    pub scope_id: Option<i64>,
+    pub url: String,
+    pub host: String,
+    pub kind: String,
+    pub message: String,
+    pub status_code: Option<i32>,
+}
+
+/// Failures of one kind on one host.
+#[derive(Debug, Clone)]
+pub struct FetchErrorSummary {
+    pub kind: String,
+    pub host: String,
+    pub count: i64,
+    /// Distinct HTTP statuses, comma separated.
+    pub statuses: Option<String>,
+    pub example_url: String,
+    pub last_seen: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FetchError {
+    pub id: i64,
+    pub run_id: Option<i64>,
+    pub scope_id: Option<i64>,
+    pub url: String,
+    pub host: String,
+    pub kind: String,
+    pub message: String,
+    pub status_code: Option<i32>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl FetchError {
+    pub fn from_row(row: &Row) -> RusqliteResult<Self> {
+        Ok(FetchError {
+            id: row.get("id")?,
+            run_id: row.get("run_id")?,
+            scope_id: row.get("scope_id")?,
+            url: row.get("url")?,
+            host: row.get("host")?,
+            kind: row.get("kind")?,
+            message: row.get("message")?,
+            status_code: row.get("status_code")?,
+            created_at: row.get("created_at")?,
+        })
+    }
+
+    pub fn create(conn: &Connection, error: &NewFetchError) -> Result<()> {
+        conn.execute(
+            "INSERT INTO fetch_errors (run_id, scope_id, url, host, kind, message, status_code, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                error.run_id,
+                error.scope_id,
+                error.url,
+                error.host,
+                error.kind,
+                error.message,
+                error.status_code,
+                Utc::now(),
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Failures grouped by kind and host, most frequent first; all runs when `run_id` is `None`.
+    pub fn summary(conn: &Connection, run_id: Option<i64>) -> Result<Vec<FetchErrorSummary>> {
+        let mut stmt = conn.prepare(
+            "SELECT kind, host, COUNT(*) AS count, GROUP_CONCAT(DISTINCT status_code) AS statuses,
+                    MAX(url) AS example_url, MAX(created_at) AS last_seen
+             FROM fetch_errors
+             WHERE ?1 IS NULL OR run_id = ?1
+             GROUP BY kind, host
+             ORDER BY count DESC, kind, host",
+        )?;
+        let rows = stmt.query_map(params![run_id], |row| {
+            Ok(FetchErrorSummary {
+                kind: row.get("kind")?,
+                host: row.get("host")?,
+                count: row.get("count")?,
+                statuses: row.get("statuses")?,
+                example_url: row.get("example_url")?,
+                last_seen: row.get("last_seen")?,
+            })
+        })?
+        .collect::<RusqliteResult<Vec<_>>>()
+        .map_err(|e| StorageError::QueryError(e.to_string()))?;
+        Ok(rows)
+    }
+}
+
+/// A URL waiting to be fetched again after failing, from the `frontier` table.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FrontierEntry {
+    pub id: i64,
+    pub scope_id: i64,
+    pub url: String,
+    pub host: String,
+    pub priority: i32,
+    pub next_allowed_at: DateTime<Utc>,
+    pub tries: u32,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl FrontierEntry {
+    pub fn from_row(row: &Row) -> RusqliteResult<Self> {
+        Ok(FrontierEntry {
+            id: row.get("id")?,
+            scope_id: row.get("scope_id")?,
+            url: row.get("url")?,
+            host: row.get("host")?,
+            priority: row.get("priority")?,
+            next_allowed_at: row.get("next_allowed_at")?,
+            tries: row.get("tries")?,
+            last_error: row.get("last_error")?,
+            created_at: row.get("created_at")?,
+        })
+    }
+
+    pub fn find_by_url(conn: &Connection, url: &str) -> Result<Option<Self>> {
+        match conn.query_row("SELECT * FROM frontier WHERE url = ?1", params![url], Self::from_row) {
+            Ok(entry) => Ok(Some(entry)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Schedules `url` for another attempt at `next_allowed_at`, counting this failure.
+    pub fn record_failure(
+        conn: &Connection,
+        scope_id: i64,
+        url: &str,
+        host: &str,
+        error: &str,
+        tries: u32,
+        next_allowed_at: DateTime<Utc>,
+    ) -> Result<()> {
+        conn.execute(
+            "INSERT INTO frontier (scope_id, url, host, next_allowed_at, tries, last_error)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+             ON CONFLICT(url) DO UPDATE SET
+                next_allowed_at = excluded.next_allowed_at,
+                tries = excluded.tries,
+                last_error = excluded.last_error",
+            params![scope_id, url, host, next_allowed_at, tries, error],
+        )?;
+        Ok(())
+    }
+
+    pub fn remove(conn: &Connection, url: &str) -> Result<usize> {
+        Ok(conn.execute("DELETE FROM frontier WHERE url = ?1", params![url])?)
+    }
+
+    /// Entries of the given scopes whose delay has passed, oldest first.
+    pub fn due(conn: &Connection, scope_ids: &[i64], now: DateTime<Utc>) -> Result<Vec<Self>> {
+        if scope_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let placeholders = vec!["?"; scope_ids.len()].join(", ");
+        let mut stmt = conn.prepare(&format!(
+            "SELECT * FROM frontier WHERE scope_id IN ({}) AND next_allowed_at <= ? ORDER BY next_allowed_at, id",
+            placeholders
+        ))?;
+        let mut values: Vec<&dyn rusqlite::ToSql> = scope_ids.iter().map(|id| id as &dyn rusqlite::ToSql).collect();
+        values.push(&now);
+        let entries = stmt.query_map(values, Self::from_row)?
+            .collect::<RusqliteResult<Vec<Self>>>()
+            .map_err(|e| StorageError::QueryError(e.to_string()))?;
+        Ok(entries)
+    }
+}
+
+/// A URL that failed too often, or in a way retrying cannot fix, and was taken out of the
+/// retry queue.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DeadLetter {
+    pub id: i64,
+    pub scope_id: i64,
+    pub url: String,
+    pub tries: u32,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl DeadLetter {
+    pub fn from_row(row: &Row) -> RusqliteResult<Self> {
+        Ok(DeadLetter {
+            id: row.get("id")?,
+            scope_id: row.get("scope_id")?,
+            url: row.get("url")?,
+            tries: row.get("tries")?,
+            last_error: row.get("last_error")?,
+            created_at: row.get("created_at")?,
+        })
+    }
+
+    /// Moves `url` to the dead-letter list, dropping it from the retry queue.
+    pub fn create(conn: &Connection, scope_id: i64, url: &str, tries: u32, error: &str) -> Result<()> {
+        FrontierEntry::remove(conn, url)?;
+        conn.execute(
+            "INSERT INTO dead_letters (scope_id, url, tries, last_error) VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(url) DO UPDATE SET
+                tries = excluded.tries,
+                last_error = excluded.last_error,
+                created_at = CURRENT_TIMESTAMP",
+            params![scope_id, url, tries, error],
+        )?;
+        Ok(())
+    }
+
+    pub fn all(conn: &Connection) -> Result<Vec<Self>> {
+        let mut stmt = conn.prepare("SELECT * FROM dead_letters ORDER BY id")?;
+        let letters = stmt.query_map(params![], Self::from_row)?
+            .collect::<RusqliteResult<Vec<Self>>>()
+            .map_err(|e| StorageError::QueryError(e.to_string()))?;
+        Ok(letters)
+    }
+
+    pub fn find_by_url(conn: &Connection, url: &str) -> Result<Option<Self>> {
+        match conn.query_row("SELECT * FROM dead_letters WHERE url = ?1", params![url], Self::from_row) {
+            Ok(letter) => Ok(Some(letter)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn find_by_id(conn: &Connection, id: i64) -> Result<Self> {
+        Ok(conn.query_row("SELECT * FROM dead_letters WHERE id = ?1", params![id], Self::from_row)
+            .map_err(|_| StorageError::NotFound)?)
+    }
+
+    /// Puts the URL back into the retry queue with a fresh attempt count, due immediately.
+    pub fn requeue(&self, conn: &Connection) -> Result<()> {
+        let host = url::Url::parse(&self.url)
+            .ok()
+            .and_then(|u| u.host_str().map(String::from))
+            .unwrap_or_default();
+        FrontierEntry::record_failure(
+            conn,
+            self.scope_id,
+            &self.url,
+            &host,
+            self.last_error.as_deref().unwrap_or_default(),
+            0,
+            Utc::now(),
+        )?;
+        conn.execute("DELETE FROM dead_letters WHERE id = ?1", params![self.id])?;
+        Ok(())
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub enum StagedUrlStatus {
     Pending,
@@ -324,6 +1014,38 @@ impl EventLevel {
             EventLevel::Debug => "DEBUG",
         }
     }
+
+    /// Parses a level name in any case, e.g. `warn`.
+    pub fn parse(level: &str) -> Option<Self> {
+        match level.to_uppercase().as_str() {
+            "INFO" => Some(EventLevel::Info),
+            "WARN" | "WARNING" => Some(EventLevel::Warn),
+            "ERROR" => Some(EventLevel::Error),
+            "DEBUG" => Some(EventLevel::Debug),
+            _ => None,
+        }
+    }
+
+    /// This level and every more severe one.
+    pub fn and_above(&self) -> &'static [EventLevel] {
+        match self {
+            EventLevel::Debug => &[EventLevel::Debug, EventLevel::Info, EventLevel::Warn, EventLevel::Error],
+            EventLevel::Info => &[EventLevel::Info, EventLevel::Warn, EventLevel::Error],
+            EventLevel::Warn => &[EventLevel::Warn, EventLevel::Error],
+            EventLevel::Error => &[EventLevel::Error],
+        }
+    }
+}
+
+/// Criteria for `Event::search`; unset fields match everything.
+#[derive(Debug, Clone, Default)]
+pub struct EventFilter {
+    pub min_level: Option<EventLevel>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    /// Substring of the message or context.
+    pub text: Option<String>,
+    pub limit: usize,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -345,28 +1067,52 @@ impl Event {
         Self::find_by_id(conn, id)
     }
 
+    pub fn from_row(row: &Row) -> RusqliteResult<Self> {
+        let level_str: String = row.get("level")?;
+        Ok(Event {
+            id: row.get("id")?,
+            timestamp: row.get("timestamp")?,
+            level: EventLevel::parse(&level_str).unwrap_or(EventLevel::Info),
+            message: row.get("message")?,
+            context: row.get("context")?,
+        })
+    }
+
     pub fn find_by_id(conn: &Connection, id: i64) -> Result<Self> {
-        Ok(conn.query_row(
-            "SELECT * FROM events WHERE id = ?1",
-            params![id],
-            |row| {
-                let level_str: String = row.get("level")?;
-                let level = match level_str.as_str() {
-                    "INFO" => EventLevel::Info,
-                    "WARN" => EventLevel::Warn,
-                    "ERROR" => EventLevel::Error,
-                    "DEBUG" => EventLevel::Debug,
-                    _ => EventLevel::Info,
-                };
-                Ok(Event {
-                    id: row.get("id")?,
-                    timestamp: row.get("timestamp")?,
-                    level,
-                    message: row.get("message")?,
-                    context: row.get("context")?,
-                })
-            },
-        )
-        .map_err(|_| StorageError::NotFound)?)
+        Ok(conn.query_row("SELECT * FROM events WHERE id = ?1", params![id], Self::from_row)
+            .map_err(|_| StorageError::NotFound)?)
+    }
+
+    /// Events matching `filter`, newest first. Timestamps are stored by SQLite's
+    /// `CURRENT_TIMESTAMP`, so bounds are compared in that format.
+    pub fn search(conn: &Connection, filter: &EventFilter) -> Result<Vec<Self>> {
+        const SQLITE_TIMESTAMP: &str = "%Y-%m-%d %H:%M:%S";
+        let levels = filter
+            .min_level
+            .as_ref()
+            .map(|level| level.and_above().iter().map(EventLevel::as_str).collect::<Vec<_>>().join(","));
+        let mut stmt = conn.prepare(
+            "SELECT * FROM events
+             WHERE (?1 IS NULL OR instr(',' || ?1 || ',', ',' || level || ',') > 0)
+               AND (?2 IS NULL OR timestamp >= ?2)
+               AND (?3 IS NULL OR timestamp <= ?3)
+               AND (?4 IS NULL OR instr(lower(message), lower(?4)) > 0
+                    OR instr(lower(COALESCE(context, '')), lower(?4)) > 0)
+             ORDER BY timestamp DESC, id DESC
+             LIMIT ?5",
+        )?;
+        let events = stmt.query_map(
+            params![
+                levels,
+                filter.since.map(|t| t.format(SQLITE_TIMESTAMP).to_string()),
+                filter.until.map(|t| t.format(SQLITE_TIMESTAMP).to_string()),
+                filter.text,
+                filter.limit as i64,
+            ],
+            Self::from_row,
+        )?
+        .collect::<RusqliteResult<Vec<Self>>>()
+        .map_err(|e| StorageError::QueryError(e.to_string()))?;
+        Ok(events)
     }
 }
@@ -17,9 +17,26 @@ CREATE TABLE IF NOT EXISTS scopes (
   keywords TEXT, -- Comma-separated keywords for NLP mode
   is_active BOOLEAN NOT NULL DEFAULT 1,
   last_crawled_at DATETIME,
+  max_pages INTEGER, -- Crawl budget of the scope; NULL means unlimited
+  max_exported INTEGER,
+  max_depth INTEGER,
+  max_bytes INTEGER,
+  max_duration INTEGER, -- Seconds
   created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
 );
 
+-- -----------------------------------------------------
+-- Table `scope_settings`
+-- Per-scope settings set with `crawlify set`, overriding the config file.
+-- -----------------------------------------------------
+CREATE TABLE IF NOT EXISTS scope_settings (
+  scope_id INTEGER NOT NULL,
+  key TEXT NOT NULL,
+  value TEXT NOT NULL,
+  PRIMARY KEY (scope_id, key),
+  FOREIGN KEY (scope_id) REFERENCES scopes (id) ON DELETE CASCADE
+);
+
 -- -----------------------------------------------------
 -- Table `staged_urls`
 -- Stores URLs discovered from sitemaps before they are added to the frontier.
@@ -47,6 +64,56 @@ CREATE TABLE IF NOT EXISTS frontier (
   priority INTEGER NOT NULL DEFAULT 0,
   next_allowed_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
   tries INTEGER NOT NULL DEFAULT 0,
+  last_error TEXT,
+  created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  FOREIGN KEY (scope_id) REFERENCES scopes (id) ON DELETE CASCADE
+);
+
+-- -----------------------------------------------------
+-- Table `crawl_runs`
+-- One row per `crawlify crawl` invocation.
+-- -----------------------------------------------------
+CREATE TABLE IF NOT EXISTS crawl_runs (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  started_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  ended_at DATETIME,
+  scopes TEXT, -- JSON array of the scope patterns crawled
+  exit_reason TEXT, -- 'completed', 'budget', 'interrupted' or 'failed'; NULL while running or if the process died
+  budget_limit TEXT, -- Limit of the run budget that stopped the run, e.g. 'max_pages'
+  pages_fetched INTEGER NOT NULL DEFAULT 0,
+  pages_exported INTEGER NOT NULL DEFAULT 0,
+  pages_duplicate INTEGER NOT NULL DEFAULT 0,
+  pages_failed INTEGER NOT NULL DEFAULT 0
+);
+
+-- -----------------------------------------------------
+-- Table `fetch_errors`
+-- Fetch, parse, export and PDF failures of crawled URLs.
+-- -----------------------------------------------------
+CREATE TABLE IF NOT EXISTS fetch_errors (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  run_id INTEGER,
+  scope_id INTEGER,
+  url TEXT NOT NULL,
+  host TEXT NOT NULL,
+  kind TEXT NOT NULL, -- CrawlifyError::kind(), e.g. timeout, server_error, parse
+  message TEXT NOT NULL,
+  status_code INTEGER,
+  created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  FOREIGN KEY (run_id) REFERENCES crawl_runs (id) ON DELETE CASCADE,
+  FOREIGN KEY (scope_id) REFERENCES scopes (id) ON DELETE SET NULL
+);
+
+-- -----------------------------------------------------
+-- Table `dead_letters`
+-- URLs taken out of the retry queue after failing too often.
+-- -----------------------------------------------------
+CREATE TABLE IF NOT EXISTS dead_letters (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  scope_id INTEGER NOT NULL,
+  url TEXT NOT NULL UNIQUE,
+  tries INTEGER NOT NULL DEFAULT 0,
+  last_error TEXT,
   created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
   FOREIGN KEY (scope_id) REFERENCES scopes (id) ON DELETE CASCADE
 );
@@ -68,6 +135,64 @@ CREATE TABLE IF NOT EXISTS pages (
   meta_json TEXT, -- JSON object for structured data (JSON-LD, OpenGraph)
   etag TEXT,
   last_modified TEXT,
+  warc_filename TEXT, -- WARC file holding the response record, when archiving is enabled
+  warc_offset INTEGER, -- Byte offset of the response record's gzip member
+  content_type TEXT,
+  body_hash TEXT, -- Key of the raw body in `bodies`, when body storage is enabled
+  redirect_chain TEXT, -- JSON array of redirect hops that led to this URL, if any
+  run_id INTEGER, -- Crawl run that last fetched the page; NULL for imported pages
+  created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- -----------------------------------------------------
+-- Table `page_aliases`
+-- URLs that redirected to a stored page.
+-- -----------------------------------------------------
+CREATE TABLE IF NOT EXISTS page_aliases (
+  alias_url TEXT PRIMARY KEY,
+  page_id INTEGER NOT NULL,
+  created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  FOREIGN KEY (page_id) REFERENCES pages (id) ON DELETE CASCADE
+);
+
+-- -----------------------------------------------------
+-- Table `page_versions`
+-- Each distinct text version of a page, in fetch order.
+-- -----------------------------------------------------
+CREATE TABLE IF NOT EXISTS page_versions (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  page_id INTEGER NOT NULL,
+  text_hash TEXT NOT NULL,
+  title TEXT,
+  text TEXT NOT NULL, -- Cleaned main content, as hashed into text_hash
+  body_hash TEXT,
+  fetched_at DATETIME NOT NULL,
+  created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  FOREIGN KEY (page_id) REFERENCES pages (id) ON DELETE CASCADE
+);
+
+-- -----------------------------------------------------
+-- Table `page_freshness`
+-- How often each page was fetched and found changed, and when to revisit it.
+-- -----------------------------------------------------
+CREATE TABLE IF NOT EXISTS page_freshness (
+  page_id INTEGER PRIMARY KEY,
+  checks INTEGER NOT NULL DEFAULT 0, -- Fetches after the first one
+  changes INTEGER NOT NULL DEFAULT 0, -- Checks that found new content
+  first_fetched_at DATETIME NOT NULL,
+  last_fetched_at DATETIME NOT NULL,
+  next_visit_at DATETIME NOT NULL,
+  FOREIGN KEY (page_id) REFERENCES pages (id) ON DELETE CASCADE
+);
+
+-- -----------------------------------------------------
+-- Table `bodies`
+-- Raw response bodies, zlib-compressed and content-addressed by SHA-1.
+-- -----------------------------------------------------
+CREATE TABLE IF NOT EXISTS bodies (
+  hash TEXT PRIMARY KEY,
+  size INTEGER NOT NULL, -- Uncompressed size in bytes
+  data BLOB NOT NULL,
   created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
 );
 
@@ -92,13 +217,16 @@ CREATE INDEX IF NOT EXISTS idx_frontier_scope_id_next_allowed_at ON frontier(sco
 DROP INDEX IF EXISTS idx_pages_url;
 CREATE INDEX IF NOT EXISTS idx_pages_url_fetched_at ON pages(url, fetched_at);
 CREATE INDEX IF NOT EXISTS idx_events_timestamp_level ON events(timestamp, level);
+CREATE INDEX IF NOT EXISTS idx_page_versions_page_id_fetched_at ON page_versions(page_id, fetched_at);
+CREATE INDEX IF NOT EXISTS idx_fetch_errors_run_id_kind ON fetch_errors(run_id, kind);
+CREATE INDEX IF NOT EXISTS idx_page_freshness_next_visit_at ON page_freshness(next_visit_at);
 
 -- -----------------------------------------------------
 -- Table `frontier_state`
 -- Stores the serialized state of the frontier for crash recovery.
 -- -----------------------------------------------------
 CREATE TABLE IF NOT EXISTS frontier_state (
-  id INTEGER PRIMARY KEY,
+  id INTEGER PRIMARY KEY, -- ID of the scope the frontier belongs to
   state BLOB NOT NULL,
   saved_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
 );
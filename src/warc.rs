@@ -0,0 +1,553 @@
+use crate::config::WarcConfig;
+use crate::error::CrawlifyError;
+use crate::http::FetchedResponse;
+use chrono::{DateTime, SecondsFormat, Utc};
+use flate2::bufread::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, CONTENT_ENCODING, CONTENT_LENGTH, TRANSFER_ENCODING};
+use reqwest::{Method, StatusCode};
+use sha1::{Digest, Sha1};
+use std::collections::VecDeque;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use url::Url;
+
+/// Location of a response record inside a WARC file.
+#[derive(Debug, Clone)]
+pub struct WarcRecordRef {
+    pub filename: String,
+    pub offset: u64,
+}
+
+struct WarcFile {
+    file: File,
+    filename: String,
+    size: u64,
+}
+
+/// Writes fetched traffic as ISO 28500 WARC/1.1 records, each record its own gzip member
+/// so readers can seek straight to a record's offset. Files rotate once they exceed
+/// `max_file_size_mb`. Every writer names its files with its own random id, so several
+/// clients, or processes, can share one WARC directory.
+pub struct WarcWriter {
+    dir: PathBuf,
+    prefix: String,
+    id: String,
+    max_file_size: u64,
+    current: Mutex<Option<WarcFile>>,
+    sequence: Mutex<u32>,
+}
+
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+fn base32(bytes: &[u8]) -> String {
+    let mut out = String::new();
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    for &byte in bytes {
+        buffer = (buffer << 8) | byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            out.push(BASE32_ALPHABET[((buffer >> bits) & 0x1f) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        out.push(BASE32_ALPHABET[((buffer << (5 - bits)) & 0x1f) as usize] as char);
+    }
+    out
+}
+
+/// `sha1:<BASE32>` digest as used by WARC-Block-Digest and WARC-Payload-Digest.
+pub fn sha1_digest(data: &[u8]) -> String {
+    format!("sha1:{}", base32(&Sha1::digest(data)))
+}
+
+/// Random (version 4) UUID in the `<urn:uuid:...>` form WARC record IDs use.
+fn record_id() -> String {
+    let mut bytes: [u8; 16] = rand::random();
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex = hex::encode(bytes);
+    format!(
+        "<urn:uuid:{}-{}-{}-{}-{}>",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    )
+}
+
+fn warc_date(date: &DateTime<Utc>) -> String {
+    date.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+fn write_headers(block: &mut Vec<u8>, headers: &HeaderMap) {
+    for (name, value) in headers {
+        block.extend_from_slice(name.as_str().as_bytes());
+        block.extend_from_slice(b": ");
+        block.extend_from_slice(value.as_bytes());
+        block.extend_from_slice(b"\r\n");
+    }
+}
+
+/// Serializes the request as it was sent. Client default headers are not visible here,
+/// which keeps configured credentials out of the archive.
+fn request_block(method: &Method, url: &Url, headers: &HeaderMap) -> Vec<u8> {
+    let mut target = url.path().to_string();
+    if let Some(query) = url.query() {
+        target.push('?');
+        target.push_str(query);
+    }
+    let mut block = format!("{} {} HTTP/1.1\r\nHost: {}\r\n", method, target, url.host_str().unwrap_or_default()).into_bytes();
+    write_headers(&mut block, headers);
+    block.extend_from_slice(b"\r\n");
+    block
+}
+
+/// Serializes the response. Bodies are stored decoded, so transfer and content encodings
+/// are dropped and Content-Length is rewritten to match.
+fn response_block(response: &FetchedResponse) -> Vec<u8> {
+    let mut headers = response.headers.clone();
+    headers.remove(CONTENT_ENCODING);
+    headers.remove(TRANSFER_ENCODING);
+    headers.insert(CONTENT_LENGTH, response.body.len().into());
+
+    let mut block = format!(
+        "HTTP/1.1 {} {}\r\n",
+        response.status.as_u16(),
+        response.status.canonical_reason().unwrap_or_default()
+    )
+    .into_bytes();
+    write_headers(&mut block, &headers);
+    block.extend_from_slice(b"\r\n");
+    block.extend_from_slice(&response.body);
+    block
+}
+
+impl WarcWriter {
+    pub fn new(config: &WarcConfig) -> Result<Self, CrawlifyError> {
+        std::fs::create_dir_all(&config.dir)?;
+        Ok(WarcWriter {
+            dir: PathBuf::from(&config.dir),
+            prefix: config.prefix.clone(),
+            id: hex::encode(rand::random::<[u8; 4]>()),
+            max_file_size: config.max_file_size_mb * 1024 * 1024,
+            current: Mutex::new(None),
+            sequence: Mutex::new(0),
+        })
+    }
+
+    fn open_next_file(&self) -> Result<WarcFile, CrawlifyError> {
+        let mut sequence = self.sequence.lock().unwrap();
+        *sequence += 1;
+        let filename = format!(
+            "{}-{}-{}-{:05}.warc.gz",
+            self.prefix,
+            Utc::now().format("%Y%m%d%H%M%S"),
+            self.id,
+            *sequence
+        );
+        // Record offsets count from the start of the file, so never append to an existing one.
+        let file = OpenOptions::new().write(true).create_new(true).open(self.dir.join(&filename))?;
+        let mut warc_file = WarcFile { file, filename, size: 0 };
+
+        let info = format!("software: crawlify/{}\r\nformat: WARC File Format 1.1\r\n", env!("CARGO_PKG_VERSION"));
+        let headers = vec![
+            ("WARC-Type", "warcinfo".to_string()),
+            ("WARC-Record-ID", record_id()),
+            ("WARC-Date", warc_date(&Utc::now())),
+            ("WARC-Filename", warc_file.filename.clone()),
+            ("Content-Type", "application/warc-fields".to_string()),
+        ];
+        Self::append_record(&mut warc_file, &headers, info.as_bytes())?;
+        Ok(warc_file)
+    }
+
+    fn append_record(warc_file: &mut WarcFile, headers: &[(&str, String)], block: &[u8]) -> Result<u64, CrawlifyError> {
+        let mut record = b"WARC/1.1\r\n".to_vec();
+        for (name, value) in headers {
+            record.extend_from_slice(format!("{}: {}\r\n", name, value).as_bytes());
+        }
+        record.extend_from_slice(format!("Content-Length: {}\r\n\r\n", block.len()).as_bytes());
+        record.extend_from_slice(block);
+        record.extend_from_slice(b"\r\n\r\n");
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&record)?;
+        let compressed = encoder.finish()?;
+
+        let offset = warc_file.size;
+        warc_file.file.write_all(&compressed)?;
+        warc_file.size += compressed.len() as u64;
+        Ok(offset)
+    }
+
+    /// Writes request, response and metadata records for one exchange and returns where
+    /// the response record starts. This compresses and writes to disk, so async callers
+    /// should run it on the blocking pool.
+    pub fn write_exchange(
+        &self,
+        method: &Method,
+        url: &Url,
+        request_headers: &HeaderMap,
+        response: &FetchedResponse,
+        elapsed_ms: u128,
+    ) -> Result<WarcRecordRef, CrawlifyError> {
+        let mut current = self.current.lock().unwrap();
+        if current.as_ref().is_none_or(|f| f.size >= self.max_file_size) {
+            *current = Some(self.open_next_file()?);
+        }
+        let warc_file = current.as_mut().unwrap();
+
+        let date = warc_date(&response.fetched_at);
+        let target_uri = url.to_string();
+        let response_id = record_id();
+
+        let block = request_block(method, url, request_headers);
+        let headers = vec![
+            ("WARC-Type", "request".to_string()),
+            ("WARC-Record-ID", record_id()),
+            ("WARC-Date", date.clone()),
+            ("WARC-Target-URI", target_uri.clone()),
+            ("WARC-Concurrent-To", response_id.clone()),
+            ("WARC-Block-Digest", sha1_digest(&block)),
+            ("Content-Type", "application/http;msgtype=request".to_string()),
+        ];
+        Self::append_record(warc_file, &headers, &block)?;
+
+        let block = response_block(response);
+        let headers = vec![
+            ("WARC-Type", "response".to_string()),
+            ("WARC-Record-ID", response_id.clone()),
+            ("WARC-Date", date.clone()),
+            ("WARC-Target-URI", target_uri.clone()),
+            ("WARC-Block-Digest", sha1_digest(&block)),
+            ("WARC-Payload-Digest", sha1_digest(&response.body)),
+            ("Content-Type", "application/http;msgtype=response".to_string()),
+        ];
+        let offset = Self::append_record(warc_file, &headers, &block)?;
+
+        let metadata = format!("fetchTimeMs: {}\r\nfinalURL: {}\r\n", elapsed_ms, response.url);
+        let headers = vec![
+            ("WARC-Type", "metadata".to_string()),
+            ("WARC-Record-ID", record_id()),
+            ("WARC-Date", date),
+            ("WARC-Target-URI", target_uri),
+            ("WARC-Refers-To", response_id),
+            ("Content-Type", "application/warc-fields".to_string()),
+        ];
+        Self::append_record(warc_file, &headers, metadata.as_bytes())?;
+
+        Ok(WarcRecordRef {
+            filename: warc_file.filename.clone(),
+            offset,
+        })
+    }
+}
+
+/// A record read back from a WARC file.
+#[derive(Debug)]
+pub struct WarcRecord {
+    pub headers: Vec<(String, String)>,
+    pub block: Vec<u8>,
+    /// Offset of the record in the file, or of the gzip member holding it.
+    pub offset: u64,
+}
+
+impl WarcRecord {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// `BufRead` wrapper that tracks how many bytes have been consumed.
+struct CountingReader<R> {
+    inner: R,
+    position: u64,
+}
+
+impl<R: BufRead> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.position += n as u64;
+        Ok(n)
+    }
+}
+
+impl<R: BufRead> BufRead for CountingReader<R> {
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.inner.consume(amt);
+        self.position += amt as u64;
+    }
+}
+
+fn invalid_data(message: &str) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, message.to_string())
+}
+
+type RawRecord = (Vec<(String, String)>, Vec<u8>);
+
+/// Reads one record's headers and block, skipping the blank lines that separate records.
+fn read_record<R: BufRead>(reader: &mut R) -> std::io::Result<Option<RawRecord>> {
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        if !line.trim().is_empty() {
+            break;
+        }
+    }
+    if !line.starts_with("WARC/") {
+        return Err(invalid_data("expected a WARC version line"));
+    }
+
+    let mut headers = Vec::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(invalid_data("truncated WARC record headers"));
+        }
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            headers.push((name.trim().to_string(), value.trim().to_string()));
+        }
+    }
+
+    let length: usize = headers
+        .iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case("Content-Length"))
+        .and_then(|(_, v)| v.parse().ok())
+        .ok_or_else(|| invalid_data("WARC record without Content-Length"))?;
+    let mut block = vec![0; length];
+    reader.read_exact(&mut block)?;
+    Ok(Some((headers, block)))
+}
+
+/// Sequential reader for plain or gzipped WARC files. Gzipped files are read one gzip
+/// member at a time so each record keeps the offset of its member.
+pub struct WarcReader {
+    reader: CountingReader<BufReader<File>>,
+    gzip: bool,
+    pending: VecDeque<WarcRecord>,
+}
+
+impl WarcReader {
+    pub fn open(path: &Path) -> std::io::Result<Self> {
+        let mut reader = CountingReader {
+            inner: BufReader::new(File::open(path)?),
+            position: 0,
+        };
+        let gzip = reader.fill_buf()?.starts_with(&[0x1f, 0x8b]);
+        Ok(WarcReader {
+            reader,
+            gzip,
+            pending: VecDeque::new(),
+        })
+    }
+
+    pub fn next_record(&mut self) -> std::io::Result<Option<WarcRecord>> {
+        if !self.gzip {
+            let offset = self.reader.position;
+            return Ok(read_record(&mut self.reader)?.map(|(headers, block)| WarcRecord { headers, block, offset }));
+        }
+
+        while self.pending.is_empty() {
+            if self.reader.fill_buf()?.is_empty() {
+                return Ok(None);
+            }
+            let offset = self.reader.position;
+            let mut member = Vec::new();
+            GzDecoder::new(&mut self.reader).read_to_end(&mut member)?;
+
+            let mut slice = &member[..];
+            while let Some((headers, block)) = read_record(&mut slice)? {
+                self.pending.push_back(WarcRecord { headers, block, offset });
+            }
+        }
+        Ok(self.pending.pop_front())
+    }
+}
+
+fn dechunk(body: &[u8]) -> Option<Vec<u8>> {
+    let mut decoded = Vec::new();
+    let mut rest = body;
+    loop {
+        let line_end = rest.windows(2).position(|w| w == b"\r\n")?;
+        let size_field = std::str::from_utf8(&rest[..line_end]).ok()?;
+        let size = usize::from_str_radix(size_field.split(';').next()?.trim(), 16).ok()?;
+        rest = &rest[line_end + 2..];
+        if size == 0 {
+            return Some(decoded);
+        }
+        decoded.extend_from_slice(rest.get(..size)?);
+        rest = rest.get(size + 2..).unwrap_or_default();
+    }
+}
+
+/// Splits the block of a `response` record into status, headers and decoded body.
+/// Chunked transfer encoding and gzip/deflate content encoding, as stored by other
+/// archivers, are undone so the body matches what a live fetch would return.
+pub fn parse_http_response(block: &[u8]) -> Option<(StatusCode, HeaderMap, Vec<u8>)> {
+    let header_end = block.windows(4).position(|w| w == b"\r\n\r\n")?;
+    let head = std::str::from_utf8(&block[..header_end]).ok()?;
+    let mut body = block[header_end + 4..].to_vec();
+
+    let mut lines = head.lines();
+    let status_line = lines.next()?;
+    if !status_line.starts_with("HTTP/") {
+        return None;
+    }
+    let status = StatusCode::from_bytes(status_line.split_whitespace().nth(1)?.as_bytes()).ok()?;
+
+    let mut headers = HeaderMap::new();
+    for line in lines {
+        if let Some((name, value)) = line.split_once(':') {
+            if let (Ok(name), Ok(value)) = (HeaderName::from_bytes(name.trim().as_bytes()), HeaderValue::from_str(value.trim())) {
+                headers.append(name, value);
+            }
+        }
+    }
+
+    let header_is = |headers: &HeaderMap, name, value: &str| {
+        headers
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v.to_ascii_lowercase().contains(value))
+    };
+    if header_is(&headers, TRANSFER_ENCODING, "chunked") {
+        body = dechunk(&body)?;
+        headers.remove(TRANSFER_ENCODING);
+    }
+    if header_is(&headers, CONTENT_ENCODING, "gzip") {
+        let mut decoded = Vec::new();
+        flate2::read::GzDecoder::new(&body[..]).read_to_end(&mut decoded).ok()?;
+        body = decoded;
+        headers.remove(CONTENT_ENCODING);
+    } else if header_is(&headers, CONTENT_ENCODING, "deflate") {
+        let mut decoded = Vec::new();
+        flate2::read::ZlibDecoder::new(&body[..]).read_to_end(&mut decoded).ok()?;
+        body = decoded;
+        headers.remove(CONTENT_ENCODING);
+    }
+    headers.insert(CONTENT_LENGTH, body.len().into());
+
+    Some((status, headers, body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::CONTENT_TYPE;
+
+    fn writer(dir: &Path) -> WarcWriter {
+        WarcWriter::new(&WarcConfig {
+            dir: dir.display().to_string(),
+            prefix: "test".to_string(),
+            max_file_size_mb: 1,
+        })
+        .unwrap()
+    }
+
+    fn html_response(url: &Url) -> FetchedResponse {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("text/html"));
+        FetchedResponse {
+            url: url.clone(),
+            status: StatusCode::OK,
+            headers,
+            body: bytes::Bytes::from_static(b"<html><title>Docs</title></html>"),
+            fetched_at: Utc::now(),
+            warc: None,
+            redirects: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn written_exchanges_read_back_as_importable_responses() {
+        let dir = std::env::temp_dir().join(format!("crawlify-warc-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let writer = writer(&dir);
+
+        let url = Url::parse("https://example.com/docs/").unwrap();
+        let response = html_response(&url);
+        let record_ref = writer.write_exchange(&Method::GET, &url, &HeaderMap::new(), &response, 12).unwrap();
+
+        let mut reader = WarcReader::open(&dir.join(&record_ref.filename)).unwrap();
+        let mut types = Vec::new();
+        let mut imported = None;
+        while let Some(record) = reader.next_record().unwrap() {
+            let warc_type = record.header("WARC-Type").unwrap().to_string();
+            if warc_type == "response" {
+                assert_eq!(record.offset, record_ref.offset);
+                assert_eq!(record.header("WARC-Target-URI"), Some(url.as_str()));
+                assert_eq!(record.header("WARC-Payload-Digest"), Some(sha1_digest(&response.body).as_str()));
+                imported = parse_http_response(&record.block);
+            }
+            types.push(warc_type);
+        }
+        assert_eq!(types, ["warcinfo", "request", "response", "metadata"]);
+
+        let (status, headers, body) = imported.expect("response record should parse");
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(headers.get(CONTENT_TYPE).unwrap(), "text/html");
+        assert_eq!(body, response.body.to_vec());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn writers_sharing_a_directory_use_separate_files() {
+        let dir = std::env::temp_dir().join(format!("crawlify-warc-shared-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let url = Url::parse("https://example.com/").unwrap();
+        let response = html_response(&url);
+
+        let first = writer(&dir).write_exchange(&Method::GET, &url, &HeaderMap::new(), &response, 1).unwrap();
+        let second = writer(&dir).write_exchange(&Method::GET, &url, &HeaderMap::new(), &response, 1).unwrap();
+        assert_ne!(first.filename, second.filename);
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 2);
+        for record_ref in [first, second] {
+            let mut reader = WarcReader::open(&dir.join(&record_ref.filename)).unwrap();
+            let mut offsets = Vec::new();
+            while let Some(record) = reader.next_record().unwrap() {
+                offsets.push(record.offset);
+            }
+            assert_eq!(offsets[0], 0);
+            assert!(offsets.contains(&record_ref.offset));
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn imported_responses_are_dechunked_and_decompressed() {
+        let chunked = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhello\r\n6\r\n world\r\n0\r\n\r\n";
+        let (_, headers, body) = parse_http_response(chunked).unwrap();
+        assert_eq!(body, b"hello world");
+        assert!(headers.get(TRANSFER_ENCODING).is_none());
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(b"compressed body").unwrap();
+        let mut gzipped = b"HTTP/1.1 200 OK\r\nContent-Encoding: gzip\r\n\r\n".to_vec();
+        gzipped.extend_from_slice(&encoder.finish().unwrap());
+        let (_, _, body) = parse_http_response(&gzipped).unwrap();
+        assert_eq!(body, b"compressed body");
+
+        assert!(parse_http_response(b"GET / HTTP/1.1\r\n\r\n").is_none());
+    }
+}